@@ -0,0 +1,991 @@
+//! Exact odds of a board, for a single player alone on it.
+//!
+//! The game is treated as a Markov chain over where a player stands between
+//! two turns: their tile, or off the board while waiting for the entry roll,
+//! and whether a skip or reverse tile is still pending. Overshoot, extra
+//! turns, three sixes, the entry roll and special tiles follow [`Rules`] the
+//! same way [`GameState`](crate::engine::GameState) does. Other players,
+//! with their bumps and swaps, cards and handicaps are left out.
+
+use std::{cell::RefCell, collections::BTreeMap};
+
+use crate::engine::{Board, ExtraTurnRule, OvershootRule, Rules, Special, ThreeSixesRule, Tile};
+
+/// Turns followed before giving up on a game that keeps going.
+const MAX_TURNS: usize = 2000;
+/// Rolls followed within a single turn of extra rolls.
+const MAX_ROLLS: usize = 50;
+/// Sweeps over the solved turns before settling for where they got.
+const MAX_SWEEPS: usize = 100;
+/// Chance of a game still going below which the chain stops.
+const EPSILON: f64 = 1e-7;
+/// Chance of a roll within a turn below which it is not followed further.
+const NEGLIGIBLE: f64 = 1e-12;
+/// Length in turns past which the summary calls a game long.
+pub const LONG_GAME: usize = 100;
+
+/// Pending [`Special::SkipTurn`].
+const SKIP: u8 = 1;
+/// Pending [`Special::Reverse`].
+const REVERSE: u8 = 2;
+
+/// Values the flags of a [`State`] can take.
+const FLAGS: usize = 4;
+/// Values the sixes rolled so far in a turn can take while it goes on.
+const SIXES: usize = 3;
+
+/// Where a player stands between two rolls.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+struct State {
+    /// Zero based tile, `None` while waiting for the entry roll.
+    tile: Option<i32>,
+    flags: u8,
+}
+
+/// Index of `state` in the dense vectors of [`Chain::play`], in the order
+/// states sort in.
+fn slot(state: State) -> usize {
+    let tile = state.tile.map_or(0, |tile| tile as usize + 1);
+    tile * FLAGS + state.flags as usize
+}
+
+/// The state at index `slot`, see [`slot`].
+fn unslot(slot: usize) -> State {
+    State {
+        tile: (slot / FLAGS).checked_sub(1).map(|tile| tile as i32),
+        flags: (slot % FLAGS) as u8,
+    }
+}
+
+/// Every distinct throw of the dice under some [`Rules`], with its chance.
+///
+/// Listing the throws goes over every face of every die, which adds up for
+/// many dice with many sides. Build the table once and hand it to
+/// [`analyze_with`] for every board played under the same rules.
+#[derive(Debug, Clone)]
+pub struct Throws {
+    rules: Rules,
+    throws: Vec<Throw>,
+}
+
+impl Throws {
+    pub fn new(rules: &Rules) -> Self {
+        Throws {
+            rules: *rules,
+            throws: throws(rules),
+        }
+    }
+
+    /// The rules the table was built for.
+    pub fn rules(&self) -> &Rules {
+        &self.rules
+    }
+
+    /// The same throws with a single roll per turn, as with
+    /// [`ExtraTurnRule::Off`] and [`ThreeSixesRule::Off`].
+    pub fn single_rolls(&self) -> Self {
+        let mut merged: BTreeMap<(u8, bool), Throw> = BTreeMap::new();
+        for throw in &self.throws {
+            let same = merged.entry((throw.roll, throw.enters)).or_insert(Throw {
+                chance: 0.0,
+                roll: throw.roll,
+                six: false,
+                enters: throw.enters,
+                singles: vec![0.0; throw.singles.len()],
+            });
+            // singles are shares of the throws, so they merge weighted by
+            // chance
+            let total = same.chance + throw.chance;
+            for (single, &other) in same.singles.iter_mut().zip(&throw.singles) {
+                *single = (*single * same.chance + other * throw.chance) / total;
+            }
+            same.chance = total;
+        }
+        Throws {
+            rules: Rules {
+                extra_turn: ExtraTurnRule::Off,
+                three_sixes: ThreeSixesRule::Off,
+                ..self.rules
+            },
+            throws: merged.into_values().collect(),
+        }
+    }
+}
+
+/// Every throw of the dice that is worth the same to the rules.
+#[derive(Debug, Clone)]
+struct Throw {
+    chance: f64,
+    roll: u8,
+    /// Earns an extra turn.
+    six: bool,
+    /// Shows the entry roll.
+    enters: bool,
+    /// Share of these throws with a die showing each face, by face. Only
+    /// kept for [`OvershootRule::Exact`] with several dice summed, where a
+    /// single die can finish.
+    singles: Vec<f64>,
+}
+
+/// Where a roll takes a player.
+#[derive(Debug)]
+struct Move {
+    to: State,
+    extra: bool,
+    snake: bool,
+    /// Landed on a teleport, `to` is spread over every free tile.
+    teleport: bool,
+    /// Tile the player ends the move on, `None` when they did not move.
+    visit: Option<i32>,
+}
+
+/// Everything that can happen in one turn started from a given state.
+#[derive(Debug, Default, Clone)]
+struct Turn {
+    /// Chance of ending the turn on each state, by [`slot`].
+    ends: Vec<f64>,
+    /// Chance of ending the turn teleported, by the flags the player ends
+    /// with. Kept apart from `ends` as it goes to every free tile alike.
+    teleports: BTreeMap<u8, f64>,
+    /// Chance of ending the turn the way a [`Spread`] does, kept apart from
+    /// `ends` for the same reason.
+    spreads: BTreeMap<Spread, f64>,
+    finished: f64,
+    rolls: f64,
+    snakes: f64,
+    /// Moves ending on each tile, by zero based index.
+    visits: Vec<f64>,
+}
+
+impl Turn {
+    /// Adds the rest of the turn `rest` after the spread `key`, reached with
+    /// `chance`. Its ends are only referred to by `key`.
+    fn follow(&mut self, key: Spread, rest: &Turn, chance: f64) {
+        *self.spreads.entry(key).or_default() += chance;
+        for (visits, p) in self.visits.iter_mut().zip(&rest.visits) {
+            *visits += chance * p;
+        }
+        self.add_totals(rest, chance, 0);
+    }
+
+    /// Adds all of `rest`, reached with `chance`, with the `skip` flag added
+    /// to where it leaves the player.
+    fn add(&mut self, rest: &Turn, chance: f64, skip: u8) {
+        for (i, p) in rest.ends.iter().enumerate() {
+            self.ends[i | skip as usize] += chance * p;
+        }
+        for (visits, p) in self.visits.iter_mut().zip(&rest.visits) {
+            *visits += chance * p;
+        }
+        self.add_totals(rest, chance, skip);
+    }
+
+    /// Adds what `rest`, reached with `chance`, leads to other than its ends
+    /// and visits, with the `skip` flag added to where it leaves the player.
+    fn add_totals(&mut self, rest: &Turn, chance: f64, skip: u8) {
+        for (&spread, p) in &rest.spreads {
+            let spread = Spread {
+                flags: spread.flags | skip,
+                ..spread
+            };
+            *self.spreads.entry(spread).or_default() += chance * p;
+        }
+        for (&flags, p) in &rest.teleports {
+            *self.teleports.entry(flags | skip).or_default() += chance * p;
+        }
+        self.finished += chance * rest.finished;
+        self.rolls += chance * rest.rolls;
+        self.snakes += chance * rest.snakes;
+    }
+
+    fn scale(&mut self, factor: f64) {
+        let values = self.ends.iter_mut().chain(self.teleports.values_mut());
+        let values = values.chain(self.spreads.values_mut());
+        for p in values.chain(self.visits.iter_mut()) {
+            *p *= factor;
+        }
+        self.finished *= factor;
+        self.rolls *= factor;
+        self.snakes *= factor;
+    }
+}
+
+/// Rest of a turn after a teleport that earned another roll. It is the same
+/// from every free tile, so it is worked out once.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+struct Spread {
+    flags: u8,
+    sixes: u8,
+    /// Tile the turn started on, only kept for [`ThreeSixesRule::BackToStart`].
+    back: Option<Option<i32>>,
+}
+
+/// Odds of a board under a set of rules, see [`analyze`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Analysis {
+    /// Chance of reaching the last tile on each turn, `length[0]` is the
+    /// first turn. Lost turns count too.
+    pub length: Vec<f64>,
+    /// Chance the game is still going after the turns in `length`. Boards
+    /// where a player can get stuck keep some here for good.
+    pub unfinished: f64,
+    pub expected_rolls: f64,
+    /// Snakes slid down in an average game.
+    pub expected_snakes: f64,
+    /// Expected number of moves ending on each tile, by zero based index.
+    /// Below one it is about the chance of standing on the tile at all.
+    pub visits: Vec<f64>,
+}
+
+impl Analysis {
+    pub fn expected_turns(&self) -> f64 {
+        self.length
+            .iter()
+            .enumerate()
+            .map(|(i, p)| (i + 1) as f64 * p)
+            .sum()
+    }
+
+    /// Chance the game lasts more than `turns` turns.
+    pub fn chance_over(&self, turns: usize) -> f64 {
+        (1.0 - self.length.iter().take(turns).sum::<f64>()).max(0.0)
+    }
+
+    /// Snakes slid down per roll.
+    pub fn snake_rate(&self) -> f64 {
+        if self.expected_rolls > 0.0 {
+            self.expected_snakes / self.expected_rolls
+        } else {
+            0.0
+        }
+    }
+
+    /// One line for the game screen, counted in turns as the chain is.
+    pub fn summary(&self) -> String {
+        if self.unfinished > 0.01 {
+            format!(
+                "{:.0}% chance of not finishing within {} turns",
+                self.unfinished * 100.0,
+                self.length.len()
+            )
+        } else {
+            format!(
+                "Average {:.0} turns, {:.0}% chance over {} turns",
+                self.expected_turns(),
+                self.chance_over(LONG_GAME) * 100.0,
+                LONG_GAME
+            )
+        }
+    }
+}
+
+struct Chain<'a> {
+    board: &'a Board,
+    rules: &'a Rules,
+    throws: &'a [Throw],
+    /// Tiles a teleport can land on.
+    free: Vec<i32>,
+    spreads: RefCell<BTreeMap<Spread, Turn>>,
+    /// Turns from every tile, see [`solve`](Self::solve).
+    solved: RefCell<BTreeMap<State, Turn>>,
+}
+
+impl<'a> Chain<'a> {
+    fn new(board: &'a Board, throws: &'a Throws) -> Self {
+        let free = (1..board.last())
+            .filter(|&i| matches!(board.get(board.position(i)), Tile::None))
+            .collect();
+        Chain {
+            board,
+            rules: &throws.rules,
+            throws: &throws.throws,
+            free,
+            spreads: RefCell::default(),
+            solved: RefCell::default(),
+        }
+    }
+
+    /// Plays out a turn from `start`, through every extra roll it can earn.
+    fn turn(&self, start: State) -> Turn {
+        if let Some(turn) = self.solved.borrow().get(&start) {
+            return turn.clone();
+        }
+        if start.flags & SKIP != 0 {
+            let mut turn = self.blank();
+            let end = State {
+                flags: start.flags & !SKIP,
+                ..start
+            };
+            turn.ends[slot(end)] = 1.0;
+            return turn;
+        }
+        self.play(start.tile, BTreeMap::from([((start, 0), 1.0)]), None)
+            .0
+    }
+
+    /// A turn where nothing happens yet.
+    fn blank(&self) -> Turn {
+        Turn {
+            ends: vec![0.0; (self.board.tiles() + 1) * FLAGS],
+            visits: vec![0.0; self.board.tiles()],
+            ..Turn::default()
+        }
+    }
+
+    /// Whether turns are [solved](Self::solve) rather than followed roll by
+    /// roll, which only pays off when the dice give extra rolls.
+    fn solving(&self) -> bool {
+        self.rules.three_sixes == ThreeSixesRule::Off && self.throws.iter().any(|t| t.six)
+    }
+
+    /// Works out the turns from every tile together. Without the three sixes
+    /// rule the rest of a turn after an extra roll is the same as a turn
+    /// started where it landed, so each turn only plays its first roll and
+    /// takes the rest from the turns of where the extra rolls land, a
+    /// teleport from all free tiles alike. A pending skip only ends a turn
+    /// before it starts and is carried along. Sweeps from the last tile back
+    /// are repeated until the turns settle, following extra rolls one at a
+    /// time gets slow on big boards with many dice.
+    fn solve(&self) {
+        if !self.solving() {
+            return;
+        }
+        let states: Vec<State> = (0..self.board.last())
+            .rev()
+            .flat_map(|tile| {
+                [0, REVERSE].map(|flags| State {
+                    tile: Some(tile),
+                    flags,
+                })
+            })
+            .collect();
+        *self.solved.borrow_mut() = states.iter().map(|&s| (s, Turn::default())).collect();
+        for _ in 0..MAX_SWEEPS {
+            let mut change: f64 = 0.0;
+            let mut settle = |new: (f64, f64), old: Option<Turn>| {
+                let old = old.unwrap_or_default();
+                change = change
+                    .max((new.0 - old.rolls).abs())
+                    .max((new.1 - old.finished).abs());
+            };
+            if !self.free.is_empty() {
+                for flags in [0, REVERSE] {
+                    let turn = self.teleported(flags);
+                    let key = Spread {
+                        flags,
+                        sixes: 0,
+                        back: None,
+                    };
+                    let new = (turn.rolls, turn.finished);
+                    settle(new, self.spreads.borrow_mut().insert(key, turn));
+                }
+            }
+            for &state in &states {
+                let (mut turn, looped) =
+                    self.play(state.tile, BTreeMap::from([((state, 0), 1.0)]), None);
+                // rolling again without moving starts the turn over
+                turn.scale(1.0 / (1.0 - looped));
+                let new = (turn.rolls, turn.finished);
+                settle(new, self.solved.borrow_mut().insert(state, turn));
+            }
+            if change < NEGLIGIBLE {
+                break;
+            }
+        }
+    }
+
+    /// Rest of a solved turn after a teleport that earned another roll,
+    /// leaving the player with `flags`.
+    fn teleported(&self, flags: u8) -> Turn {
+        let share = 1.0 / self.free.len() as f64;
+        let solved = self.solved.borrow();
+        let mut turn = self.blank();
+        for &tile in &self.free {
+            let state = State {
+                tile: Some(tile),
+                flags,
+            };
+            turn.add(&solved[&state], share, 0);
+            turn.visits[tile as usize] += share;
+        }
+        turn
+    }
+
+    /// What a teleport that earned another roll leads to, see [`Spread`].
+    fn spread(&self, key: Spread) -> Turn {
+        let share = 1.0 / self.free.len() as f64;
+        let rolling = self
+            .free
+            .iter()
+            .map(|&tile| {
+                let state = State {
+                    tile: Some(tile),
+                    flags: key.flags,
+                };
+                ((state, key.sixes), share)
+            })
+            .collect();
+        let (mut turn, looped) = self.play(key.back.flatten(), rolling, Some(key));
+        for &tile in &self.free {
+            turn.visits[tile as usize] += share;
+        }
+        // teleporting into the same spread again starts it over
+        turn.scale(1.0 / (1.0 - looped));
+        turn
+    }
+
+    /// Rolls on from `rolling`, states with the number of sixes rolled this
+    /// turn, until the turn is over. Also gives the chance of coming back to
+    /// `spread`, which is left out of the turn, or while
+    /// [solving](Self::solve) of rolling again from the single state in
+    /// `rolling`.
+    fn play(
+        &self,
+        start: Option<i32>,
+        rolling: BTreeMap<(State, u8), f64>,
+        spread: Option<Spread>,
+    ) -> (Turn, f64) {
+        let mut turn = self.blank();
+        let mut looped = 0.0;
+        let last = self.board.last();
+        let own = match rolling.keys().collect::<Vec<_>>()[..] {
+            [&(state, _)] if spread.is_none() => Some(state),
+            _ => None,
+        };
+        // dense by slot too, a turn on a big board with extra rolls touches
+        // most of it
+        let states = (self.board.tiles() + 1) * FLAGS;
+        let mut rolling = {
+            let mut dense = vec![0.0; states * SIXES];
+            for ((state, sixes), chance) in rolling {
+                dense[slot(state) * SIXES + sixes as usize] += chance;
+            }
+            dense
+        };
+        for _ in 0..MAX_ROLLS {
+            let mut next = vec![0.0; states * SIXES];
+            // teleports that earned another roll, by the flags they leave,
+            // while solving
+            let mut teleported: BTreeMap<u8, f64> = BTreeMap::new();
+            for (i, &chance) in rolling.iter().enumerate() {
+                if chance == 0.0 {
+                    continue;
+                }
+                let (state, sixes) = (unslot(i / SIXES), (i % SIXES) as u8);
+                turn.rolls += chance;
+                for throw in self.throws {
+                    let chance = chance * throw.chance;
+                    // without the three sixes rule the count does not matter
+                    let sixes = match self.rules.three_sixes {
+                        ThreeSixesRule::Off => 0,
+                        _ => sixes + u8::from(throw.six),
+                    };
+                    if throw.six && sixes >= 3 && self.rules.three_sixes != ThreeSixesRule::Off {
+                        let end = match self.rules.three_sixes {
+                            ThreeSixesRule::BackToStart => State {
+                                tile: start,
+                                ..state
+                            },
+                            _ => state,
+                        };
+                        turn.ends[slot(end)] += chance;
+                        continue;
+                    }
+                    let chance = match self.single_finish(state, throw) {
+                        Some(share) => {
+                            turn.visits[last as usize] += chance * share;
+                            turn.finished += chance * share;
+                            chance * (1.0 - share)
+                        }
+                        None => chance,
+                    };
+                    let m = self.moves(state, throw);
+                    if m.snake {
+                        turn.snakes += chance;
+                    }
+                    if m.teleport && m.extra && self.solving() {
+                        *teleported.entry(m.to.flags).or_default() += chance;
+                        continue;
+                    }
+                    if m.teleport && m.extra {
+                        let key = Spread {
+                            flags: m.to.flags,
+                            sixes,
+                            back: (self.rules.three_sixes == ThreeSixesRule::BackToStart)
+                                .then_some(start),
+                        };
+                        if Some(key) == spread {
+                            looped += chance;
+                        } else {
+                            if !self.spreads.borrow().contains_key(&key) {
+                                let rest = self.spread(key);
+                                self.spreads.borrow_mut().insert(key, rest);
+                            }
+                            turn.follow(key, &self.spreads.borrow()[&key], chance);
+                        }
+                        continue;
+                    }
+                    if m.teleport {
+                        let share = chance / self.free.len() as f64;
+                        for &tile in &self.free {
+                            turn.visits[tile as usize] += share;
+                        }
+                        *turn.teleports.entry(m.to.flags).or_default() += chance;
+                        continue;
+                    }
+                    if let Some(tile) = m.visit {
+                        turn.visits[tile as usize] += chance;
+                    }
+                    if m.to.tile == Some(last) {
+                        turn.finished += chance;
+                    } else if m.extra {
+                        next[slot(m.to) * SIXES + sixes as usize] += chance;
+                    } else {
+                        turn.ends[slot(m.to)] += chance;
+                    }
+                }
+            }
+            // extra rolls landing on a solved turn take the rest of it from
+            // there, a pending skip stays pending
+            if self.solving() {
+                let solved = self.solved.borrow();
+                let spreads = self.spreads.borrow();
+                let mut rests = Vec::new();
+                for (i, chance) in next.iter_mut().enumerate() {
+                    let state = unslot(i / SIXES);
+                    let plain = State {
+                        flags: state.flags & !SKIP,
+                        ..state
+                    };
+                    if *chance == 0.0 {
+                        continue;
+                    } else if Some(state) == own && solved.contains_key(&state) {
+                        looped += std::mem::take(chance);
+                    } else if let Some(rest) = solved.get(&plain) {
+                        rests.push((rest, state.flags & SKIP, std::mem::take(chance)));
+                    }
+                }
+                for (flags, chance) in teleported {
+                    let key = Spread {
+                        flags: flags & !SKIP,
+                        sixes: 0,
+                        back: None,
+                    };
+                    if let Some(rest) = spreads.get(&key) {
+                        rests.push((rest, flags & SKIP, chance));
+                    }
+                }
+                for (rest, skip, chance) in rests {
+                    turn.add(rest, chance, skip);
+                }
+            }
+            // long chains of extra rolls that hardly ever happen are cut
+            // off where they stand
+            let mut more = false;
+            for (i, chance) in next.iter_mut().enumerate() {
+                if *chance < NEGLIGIBLE {
+                    turn.ends[i / SIXES] += std::mem::take(chance);
+                } else {
+                    more = true;
+                }
+            }
+            rolling = next;
+            if !more {
+                break;
+            }
+        }
+        // a chain of extra rolls this long is cut off where it stands
+        for (i, chance) in rolling.into_iter().enumerate() {
+            turn.ends[i / SIXES] += chance;
+        }
+        (turn, looped)
+    }
+
+    /// Share of `throw` that finishes with a single die, when the sum
+    /// overshoots the last tile.
+    fn single_finish(&self, state: State, throw: &Throw) -> Option<f64> {
+        let from = state.tile?;
+        let need = self.board.last() - from;
+        if state.flags & REVERSE != 0 || i32::from(throw.roll) <= need {
+            return None;
+        }
+        throw.singles.get(need as usize).copied()
+    }
+
+    /// Where `throw` takes a player standing on `state`.
+    fn moves(&self, state: State, throw: &Throw) -> Move {
+        let Some(from) = state.tile else {
+            if self.rules.entry_roll.is_some() && throw.enters {
+                return Move {
+                    to: State {
+                        tile: Some(0),
+                        ..state
+                    },
+                    extra: throw.six,
+                    snake: false,
+                    teleport: false,
+                    visit: Some(0),
+                };
+            }
+            return Move {
+                to: state,
+                extra: false,
+                snake: false,
+                teleport: false,
+                visit: None,
+            };
+        };
+
+        let last = self.board.last();
+        let roll = i32::from(throw.roll);
+        let mut flags = state.flags;
+        let mut new_pos = from + roll;
+        if flags & REVERSE != 0 {
+            flags &= !REVERSE;
+            new_pos = (from - roll).max(0);
+        } else if new_pos > last {
+            match self.rules.overshoot {
+                OvershootRule::Exact => {
+                    return Move {
+                        to: state,
+                        extra: throw.six,
+                        snake: false,
+                        teleport: false,
+                        visit: None,
+                    };
+                }
+                OvershootRule::Bounce => new_pos = (last - (new_pos - last)).max(0),
+                OvershootRule::Pass => new_pos = last,
+            }
+        }
+
+        let mut extra = throw.six;
+        let mut snake = false;
+        let mut teleport = false;
+        let landed = self.board.position(new_pos);
+        let tile = match self.board.get(landed) {
+            Tile::Snake(to) => {
+                snake = true;
+                self.board.index(to)
+            }
+            Tile::Ladder(to) => self.board.index(to),
+            Tile::Special(Special::SkipTurn) => {
+                flags |= SKIP;
+                new_pos
+            }
+            Tile::Special(Special::ExtraRoll) => {
+                extra = true;
+                new_pos
+            }
+            Tile::Special(Special::Reverse) => {
+                flags |= REVERSE;
+                new_pos
+            }
+            Tile::Special(Special::Teleport) => {
+                teleport = !self.free.is_empty();
+                new_pos
+            }
+            _ => new_pos,
+        };
+        Move {
+            to: State {
+                tile: Some(tile),
+                flags,
+            },
+            extra,
+            snake,
+            teleport,
+            visit: Some(tile),
+        }
+    }
+}
+
+/// Every distinct throw of the dice in `rules`, with its chance.
+fn throws(rules: &Rules) -> Vec<Throw> {
+    let dice = &rules.dice;
+    let total = (dice.sides as usize).pow(dice.count as u32);
+    let singles = rules.overshoot == OvershootRule::Exact && dice.count > 1 && !dice.needs_pick();
+    let mut seen: BTreeMap<(u8, bool, bool), (usize, Vec<usize>)> = BTreeMap::new();
+    let mut faces = vec![1u8; dice.count as usize];
+    for _ in 0..total {
+        let roll = if dice.needs_pick() {
+            // the highest die, as the game picks for players who do not
+            faces.iter().copied().max().unwrap_or_default()
+        } else {
+            faces.iter().sum()
+        };
+        let six = rules.extra_turn.grants(&faces, dice.sides);
+        let enters = rules.entry_roll.is_some_and(|e| faces.contains(&e));
+        let (count, faces_shown) = seen.entry((roll, six, enters)).or_default();
+        *count += 1;
+        if singles {
+            faces_shown.resize(dice.sides as usize + 1, 0);
+            for face in 1..=dice.sides {
+                if faces.contains(&face) {
+                    faces_shown[face as usize] += 1;
+                }
+            }
+        }
+        // next combination, counting in base `sides`
+        for face in faces.iter_mut() {
+            if *face < dice.sides {
+                *face += 1;
+                break;
+            }
+            *face = 1;
+        }
+    }
+    seen.into_iter()
+        .map(|((roll, six, enters), (count, faces_shown))| Throw {
+            chance: count as f64 / total as f64,
+            roll,
+            six,
+            enters,
+            singles: faces_shown
+                .into_iter()
+                .map(|shown| shown as f64 / count as f64)
+                .collect(),
+        })
+        .collect()
+}
+
+/// Works out how a game on `board` goes for a single player under `rules`.
+pub fn analyze(board: &Board, rules: &Rules) -> Analysis {
+    analyze_with(board, &Throws::new(rules))
+}
+
+/// [`analyze`] with a throw table built beforehand, under its rules.
+pub fn analyze_with(board: &Board, throws: &Throws) -> Analysis {
+    let rules = &throws.rules;
+    let chain = Chain::new(board, throws);
+    chain.solve();
+    let start = State {
+        tile: rules.entry_roll.is_none().then_some(0),
+        flags: 0,
+    };
+    // every state a game can reach, numbered so the turns below can be
+    // followed on plain vectors
+    let mut states = vec![start];
+    let mut index = BTreeMap::from([(start, 0)]);
+    let mut turns = Vec::new();
+    let mut number = |state: State, states: &mut Vec<State>| {
+        *index.entry(state).or_insert_with(|| {
+            states.push(state);
+            states.len() - 1
+        })
+    };
+    // ends shared by many turns, by the flags a teleport leaves and by
+    // spread
+    let mut teleports: BTreeMap<u8, Vec<usize>> = BTreeMap::new();
+    let mut spreads: BTreeMap<Spread, Vec<(usize, f64)>> = BTreeMap::new();
+    let mut ends: Vec<Vec<(usize, f64)>> = Vec::new();
+    while turns.len() < states.len() {
+        let turn = chain.turn(states[turns.len()]);
+        ends.push(
+            turn.ends
+                .iter()
+                .enumerate()
+                .filter(|&(_, &p)| p > 0.0)
+                .map(|(i, &p)| (number(unslot(i), &mut states), p))
+                .collect(),
+        );
+        for &flags in turn.teleports.keys() {
+            teleports.entry(flags).or_insert_with(|| {
+                chain
+                    .free
+                    .iter()
+                    .map(|&tile| {
+                        let state = State {
+                            tile: Some(tile),
+                            flags,
+                        };
+                        number(state, &mut states)
+                    })
+                    .collect()
+            });
+        }
+        for &key in turn.spreads.keys() {
+            spreads.entry(key).or_insert_with(|| {
+                chain.spreads.borrow()[&key]
+                    .ends
+                    .iter()
+                    .enumerate()
+                    .filter(|&(_, &p)| p > 0.0)
+                    .map(|(i, &p)| (number(unslot(i), &mut states), p))
+                    .collect()
+            });
+        }
+        turns.push(turn);
+    }
+
+    let mut analysis = Analysis {
+        length: Vec::new(),
+        unfinished: 1.0,
+        expected_rolls: 0.0,
+        expected_snakes: 0.0,
+        visits: vec![0.0; board.tiles()],
+    };
+    let mut playing = vec![0.0; turns.len()];
+    playing[0] = 1.0;
+    // chance of having been in each state at the start of some turn
+    let mut occupied = vec![0.0; turns.len()];
+    while analysis.unfinished > EPSILON && analysis.length.len() < MAX_TURNS {
+        let mut next = vec![0.0; turns.len()];
+        let mut finished = 0.0;
+        let mut teleported: BTreeMap<u8, f64> = BTreeMap::new();
+        let mut spread: BTreeMap<Spread, f64> = BTreeMap::new();
+        for (i, &chance) in playing.iter().enumerate() {
+            if chance == 0.0 {
+                continue;
+            }
+            occupied[i] += chance;
+            finished += chance * turns[i].finished;
+            for &(end, p) in &ends[i] {
+                next[end] += chance * p;
+            }
+            for (&flags, p) in &turns[i].teleports {
+                *teleported.entry(flags).or_default() += chance * p;
+            }
+            for (&key, p) in &turns[i].spreads {
+                *spread.entry(key).or_default() += chance * p;
+            }
+        }
+        for (flags, chance) in teleported {
+            let share = chance / chain.free.len() as f64;
+            for &end in &teleports[&flags] {
+                next[end] += share;
+            }
+        }
+        for (key, chance) in spread {
+            for &(end, p) in &spreads[&key] {
+                next[end] += chance * p;
+            }
+        }
+        analysis.length.push(finished);
+        analysis.unfinished = next.iter().sum();
+        playing = next;
+    }
+    for (turn, chance) in turns.iter().zip(occupied) {
+        analysis.expected_rolls += chance * turn.rolls;
+        analysis.expected_snakes += chance * turn.snakes;
+        for (total, visits) in analysis.visits.iter_mut().zip(&turn.visits) {
+            *total += chance * visits;
+        }
+    }
+    analysis
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dice::SeededDice,
+        engine::{BoardSize, BoardSpec, ExtraTurnRule, GameRng, GameState, Player},
+    };
+    use rand::SeedableRng;
+
+    fn empty(width: usize, height: usize) -> Board {
+        Board::empty(BoardSize { width, height })
+    }
+
+    fn single_rolls(overshoot: OvershootRule) -> Rules {
+        Rules {
+            overshoot,
+            extra_turn: ExtraTurnRule::Off,
+            ..Rules::default()
+        }
+    }
+
+    /// Equal but for the games the chain stops following, see [`EPSILON`].
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-5
+    }
+
+    #[test]
+    fn empty_board_takes_the_rolls_to_add_up_to_its_length() {
+        let board = empty(5, 5);
+        let odds = analyze(&board, &single_rolls(OvershootRule::Pass));
+        // rolls[n] are the rolls a single die needs to add up to n or more
+        let mut rolls = [0.0; 25];
+        for n in 1..rolls.len() {
+            rolls[n] = 1.0 + (1..=6).map(|k| rolls[n.saturating_sub(k)]).sum::<f64>() / 6.0;
+        }
+        assert!(
+            close(odds.expected_rolls, rolls[24]),
+            "{}",
+            odds.expected_rolls
+        );
+        assert!(close(odds.expected_turns(), rolls[24]));
+        assert!(close(odds.length.iter().sum(), 1.0));
+        assert_eq!(odds.expected_snakes, 0.0);
+        // three sixes only get to tile 18
+        assert_eq!(odds.length[..3], [0.0; 3]);
+    }
+
+    #[test]
+    fn single_snake_sends_back_as_often_as_it_is_landed_on() {
+        // tiles 0 to 3, the snake on tile 2 leads back to the start
+        let mut board = empty(2, 2);
+        let pos = board.position(2);
+        board.tile[pos.y as usize][pos.x as usize] = Tile::Snake(board.position(0));
+        let odds = analyze(&board, &single_rolls(OvershootRule::Pass));
+        // from the start 3 or more finishes, 2 hits the snake and 1 leaves a
+        // 1 in 6 chance of hitting it with the next roll, so
+        // rolls = 1 + rolls / 6 + (1 + rolls / 6) / 6
+        assert!(
+            close(odds.expected_rolls, 42.0 / 29.0),
+            "{}",
+            odds.expected_rolls
+        );
+        assert!(
+            close(odds.expected_snakes, 7.0 / 29.0),
+            "{}",
+            odds.expected_snakes
+        );
+        assert!(odds.unfinished < 1e-6);
+    }
+
+    #[test]
+    fn board_that_cannot_be_finished_stays_unfinished() {
+        // every roll from tile 10 lands on a snake back to it, and no roll
+        // gets past them from further down
+        let mut board = empty(5, 5);
+        for from in 11..=16 {
+            let pos = board.position(from);
+            board.tile[pos.y as usize][pos.x as usize] = Tile::Snake(board.position(10));
+        }
+        let odds = analyze(&board, &single_rolls(OvershootRule::Exact));
+        assert!(odds.unfinished > 0.99, "{}", odds.unfinished);
+        assert!(odds.length.iter().sum::<f64>() < 0.01);
+        assert!(odds.summary().contains("chance of not finishing"));
+    }
+
+    #[test]
+    fn matches_played_out_games() {
+        let spec = BoardSpec::new(BoardSize {
+            width: 6,
+            height: 6,
+        });
+        let board = Board::generate(&spec, &mut GameRng::seed_from_u64(5)).unwrap();
+        // extra turns on a six are kept, as they are the hardest to follow
+        let rules = Rules::default();
+        let odds = analyze(&board, &rules);
+        let mut dice = SeededDice::new(GameRng::seed_from_u64(6));
+        let games = 4000;
+        let mut rolls = 0;
+        for seed in 0..games {
+            let players = vec![Player::new("A")];
+            let mut state =
+                GameState::new(board.clone(), players, rules, GameRng::seed_from_u64(seed));
+            while state.play_turn(&mut dice).is_some() {
+                rolls += 1;
+            }
+        }
+        let played = rolls as f64 / games as f64;
+        assert!(
+            (played - odds.expected_rolls).abs() < 0.05 * odds.expected_rolls,
+            "{played} rolls played, {} expected",
+            odds.expected_rolls
+        );
+    }
+}
@@ -0,0 +1,388 @@
+//! Short codes for sharing a board and the rules it is played with.
+//!
+//! A code packs the board size, the rules and every snake, ladder, safe tile
+//! and special tile into a few bytes, followed by a Fletcher-16 checksum,
+//! written in Crockford's base32. Codes are read case-insensitively and the
+//! board name and author are left out.
+
+use crate::{
+    board_file::{BoardError, BoardFile, Link, SpecialTile},
+    dice::{Combine, DICE_COUNTS, DICE_SIDES, DiceConfig},
+    engine::{
+        Board, ExtraTurnRule, Mode, OvershootRule, Rules, Special, TeamWin, ThreeSixesRule, Tile,
+    },
+};
+
+/// Layout of the bytes, bumped when older codes can no longer be read.
+const VERSION: u8 = 1;
+
+const ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+// kinds of the tile entries, specials follow in `Special::ALL` order
+const SNAKE: u32 = 0;
+const LADDER: u32 = 1;
+const SAFE: u32 = 2;
+const SPECIAL: u32 = 3;
+
+/// Why [`decode`] could not read a code.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum CodeError {
+    /// A character that is not part of the alphabet.
+    Character(char),
+    /// The checksum does not match, most likely a typo.
+    Checksum,
+    /// Made by another version of the game.
+    Version(u8),
+    /// The code is cut off or makes no sense.
+    Malformed,
+    /// The rules are not valid.
+    Rules,
+    Board(BoardError),
+}
+
+impl std::fmt::Display for CodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CodeError::Character(c) => write!(f, "'{c}' does not belong in a board code"),
+            CodeError::Checksum => f.write_str("the board code has a typo"),
+            CodeError::Version(version) => {
+                write!(f, "the board code is from another version ({version})")
+            }
+            CodeError::Malformed => f.write_str("the board code is damaged"),
+            CodeError::Rules => f.write_str("the board code has broken rules"),
+            CodeError::Board(err) => write!(f, "the board code has a broken board: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for CodeError {}
+
+/// The code of `board` played with `rules`. The size must be within
+/// [`BOARD_DIMENSIONS`](crate::engine::BOARD_DIMENSIONS), as for every board the game makes or loads.
+pub fn encode(board: &Board, rules: &Rules) -> String {
+    let mut bytes = vec![
+        VERSION,
+        board.width as u8,
+        board.height as u8,
+        rules.mode as u8
+            | (rules.overshoot as u8) << 2
+            | (rules.extra_turn as u8) << 4
+            | (rules.three_sixes as u8) << 6,
+        rules.dice.count,
+        rules.dice.sides,
+        rules.dice.combine as u8
+            | u8::from(rules.cards) << 1
+            | rules.teams.map_or(0, |win| win as u8 + 1) << 2,
+        rules.entry_roll.unwrap_or(0),
+    ];
+    // every entry is the tiles skipped since the previous one and its kind,
+    // snakes and ladders add how far they go
+    let mut gap = 0;
+    for index in 0..board.tiles() as i32 {
+        let (kind, length) = match board.get(board.position(index)) {
+            Tile::Snake(to) => (SNAKE, Some(index - board.index(to))),
+            Tile::Ladder(to) => (LADDER, Some(board.index(to) - index)),
+            Tile::Safe => (SAFE, None),
+            Tile::Special(special) => (SPECIAL + special as u32, None),
+            Tile::Target | Tile::None => {
+                gap += 1;
+                continue;
+            }
+        };
+        push_varint(&mut bytes, gap << 3 | kind);
+        if let Some(length) = length {
+            push_varint(&mut bytes, length as u32);
+        }
+        gap = 0;
+    }
+    bytes.extend(fletcher16(&bytes).to_be_bytes());
+    to_base32(&bytes)
+}
+
+/// Reads back a code made by [`encode`].
+pub fn decode(code: &str) -> Result<(Board, Rules), CodeError> {
+    let mut bytes = from_base32(code)?;
+    if bytes.len() < 2 {
+        return Err(CodeError::Malformed);
+    }
+    let sum = bytes.split_off(bytes.len() - 2);
+    if fletcher16(&bytes).to_be_bytes() != sum[..] {
+        return Err(CodeError::Checksum);
+    }
+    let mut reader = Reader { bytes: &bytes };
+    let version = reader.byte()?;
+    if version != VERSION {
+        return Err(CodeError::Version(version));
+    }
+    let width = reader.byte()? as usize;
+    let height = reader.byte()? as usize;
+    let rules = read_rules(&mut reader)?;
+
+    let mut file = BoardFile {
+        width,
+        height,
+        ..BoardFile::default()
+    };
+    let mut tile: i32 = 0;
+    while !reader.bytes.is_empty() {
+        let entry = reader.varint()?;
+        tile = tile.saturating_add((entry >> 3) as i32 + 1);
+        match entry & 7 {
+            SNAKE => {
+                let length = reader.varint()? as i32;
+                file.snakes.push(Link {
+                    from: tile,
+                    to: tile.saturating_sub(length),
+                });
+            }
+            LADDER => {
+                let length = reader.varint()? as i32;
+                file.ladders.push(Link {
+                    from: tile,
+                    to: tile.saturating_add(length),
+                });
+            }
+            SAFE => file.safe.push(tile),
+            kind => {
+                let effect = *Special::ALL
+                    .get((kind - SPECIAL) as usize)
+                    .ok_or(CodeError::Malformed)?;
+                file.specials.push(SpecialTile { tile, effect });
+            }
+        }
+    }
+    let board = Board::try_from(file).map_err(CodeError::Board)?;
+    Ok((board, rules))
+}
+
+fn read_rules(reader: &mut Reader) -> Result<Rules, CodeError> {
+    fn pick<T: Copy>(all: &[T], i: u8) -> Result<T, CodeError> {
+        all.get(i as usize).copied().ok_or(CodeError::Rules)
+    }
+    let flags = reader.byte()?;
+    let count = reader.byte()?;
+    let sides = reader.byte()?;
+    let more = reader.byte()?;
+    let entry = reader.byte()?;
+    let rules = Rules {
+        mode: pick(&[Mode::Friendly, Mode::Bump, Mode::Swap], flags & 3)?,
+        overshoot: pick(
+            &[
+                OvershootRule::Exact,
+                OvershootRule::Bounce,
+                OvershootRule::Pass,
+            ],
+            flags >> 2 & 3,
+        )?,
+        extra_turn: pick(
+            &[
+                ExtraTurnRule::Off,
+                ExtraTurnRule::Six,
+                ExtraTurnRule::Doubles,
+            ],
+            flags >> 4 & 3,
+        )?,
+        three_sixes: pick(
+            &[
+                ThreeSixesRule::Off,
+                ThreeSixesRule::Forfeit,
+                ThreeSixesRule::BackToStart,
+            ],
+            flags >> 6,
+        )?,
+        dice: DiceConfig {
+            count,
+            sides,
+            combine: pick(&[Combine::Sum, Combine::Pick], more & 1)?,
+        },
+        cards: more >> 1 & 1 == 1,
+        teams: pick(&[None, Some(TeamWin::Any), Some(TeamWin::All)], more >> 2)?,
+        entry_roll: (entry != 0).then_some(entry),
+    };
+    if !DICE_COUNTS.contains(&count) || !DICE_SIDES.contains(&sides) || entry > sides {
+        return Err(CodeError::Rules);
+    }
+    Ok(rules)
+}
+
+/// Bytes of a code still to be read.
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl Reader<'_> {
+    fn byte(&mut self) -> Result<u8, CodeError> {
+        let (&first, rest) = self.bytes.split_first().ok_or(CodeError::Malformed)?;
+        self.bytes = rest;
+        Ok(first)
+    }
+
+    fn varint(&mut self) -> Result<u32, CodeError> {
+        let mut value = 0u32;
+        for shift in (0..32).step_by(7) {
+            let byte = self.byte()?;
+            value |= u32::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(CodeError::Malformed)
+    }
+}
+
+/// Seven bits per byte, the high bit set on all but the last.
+fn push_varint(bytes: &mut Vec<u8>, mut value: u32) {
+    while value >= 0x80 {
+        bytes.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+fn fletcher16(bytes: &[u8]) -> u16 {
+    let (mut a, mut b) = (0u16, 0u16);
+    for &byte in bytes {
+        a = (a + u16::from(byte)) % 255;
+        b = (b + a) % 255;
+    }
+    b << 8 | a
+}
+
+fn to_base32(bytes: &[u8]) -> String {
+    let mut code = String::new();
+    let (mut buffer, mut bits) = (0u32, 0);
+    for &byte in bytes {
+        buffer = buffer << 8 | u32::from(byte);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            code.push(ALPHABET[(buffer >> bits & 31) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        code.push(ALPHABET[(buffer << (5 - bits) & 31) as usize] as char);
+    }
+    code
+}
+
+/// Decodes Crockford's base32, taking `I` and `L` for `1`, `O` for `0` and
+/// skipping whitespace and dashes.
+fn from_base32(code: &str) -> Result<Vec<u8>, CodeError> {
+    let mut bytes = Vec::new();
+    let (mut buffer, mut bits) = (0u32, 0);
+    for c in code.chars() {
+        let digit = match c.to_ascii_uppercase() {
+            c if c.is_whitespace() || c == '-' => continue,
+            'I' | 'L' => 1,
+            'O' => 0,
+            upper => ALPHABET
+                .iter()
+                .position(|&a| a as char == upper)
+                .ok_or(CodeError::Character(c))? as u32,
+        };
+        buffer = (buffer << 5 | digit) & 0xfff;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+        }
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{engine::BoardSize, presets::PRESETS};
+
+    fn rules() -> Rules {
+        Rules {
+            mode: Mode::Swap,
+            overshoot: OvershootRule::Bounce,
+            extra_turn: ExtraTurnRule::Doubles,
+            three_sixes: ThreeSixesRule::BackToStart,
+            dice: DiceConfig {
+                count: 3,
+                sides: 8,
+                combine: Combine::Pick,
+            },
+            entry_roll: Some(8),
+            teams: Some(TeamWin::All),
+            cards: true,
+        }
+    }
+
+    fn layout(board: Board) -> BoardFile {
+        BoardFile {
+            name: String::new(),
+            author: String::new(),
+            ..board.into()
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        for preset in PRESETS {
+            let board = preset.board();
+            let (back, read) = decode(&encode(&board, &rules())).unwrap();
+            assert_eq!(layout(back), layout(board), "{preset}");
+            assert_eq!(read, rules());
+        }
+        let (_, read) = decode(&encode(&PRESETS[0].board(), &Rules::default())).unwrap();
+        assert_eq!(read, Rules::default());
+    }
+
+    #[test]
+    fn reads_lowercase_and_look_alikes() {
+        let code = encode(&PRESETS[0].board(), &rules());
+        let sloppy: String = code
+            .to_lowercase()
+            .replace('1', "l")
+            .replace('0', "O")
+            .chars()
+            .flat_map(|c| [c, ' '])
+            .collect();
+        assert_eq!(decode(&sloppy).unwrap().1, rules());
+    }
+
+    #[test]
+    fn rejects_typos() {
+        let code = encode(&PRESETS[0].board(), &rules());
+        let mut typo = code.clone().into_bytes();
+        typo[5] = if typo[5] == b'A' { b'B' } else { b'A' };
+        let typo = String::from_utf8(typo).unwrap();
+        assert_eq!(decode(&typo).unwrap_err(), CodeError::Checksum);
+        assert_eq!(
+            decode(&format!("{code}U")).unwrap_err(),
+            CodeError::Character('U')
+        );
+        assert_eq!(decode("").unwrap_err(), CodeError::Malformed);
+    }
+
+    #[test]
+    fn rejects_dice_and_sizes_the_game_does_not_offer() {
+        let board = PRESETS[0].board();
+        for (count, sides) in [(0, 6), (5, 6), (1, 7), (1, 255)] {
+            let rules = Rules {
+                dice: DiceConfig {
+                    count,
+                    sides,
+                    combine: Combine::Sum,
+                },
+                ..Rules::default()
+            };
+            assert_eq!(
+                decode(&encode(&board, &rules)).unwrap_err(),
+                CodeError::Rules
+            );
+        }
+        let wide = Board::empty(BoardSize {
+            width: 21,
+            height: 5,
+        });
+        assert_eq!(
+            decode(&encode(&wide, &Rules::default())).unwrap_err(),
+            CodeError::Board(BoardError::Size)
+        );
+    }
+}
@@ -0,0 +1,294 @@
+//! Board files, for boards designed by hand and shared.
+//!
+//! A board is stored as JSON. Tiles are numbered as on screen, from 1 in the
+//! bottom left corner up to `width * height`, the winning tile:
+//!
+//! ```json
+//! {
+//!   "name": "Garden",
+//!   "author": "Atharva",
+//!   "width": 10,
+//!   "height": 10,
+//!   "snakes": [{ "from": 17, "to": 7 }, { "from": 98, "to": 78 }],
+//!   "ladders": [{ "from": 4, "to": 14 }, { "from": 80, "to": 100 }],
+//!   "safe": [25, 50],
+//!   "specials": [{ "tile": 33, "effect": "teleport" }]
+//! }
+//! ```
+//!
+//! Boards are 5 to 20 tiles wide and high and everything but `width` and
+//! `height` may be left out. The effects are
+//! `skip_turn`, `extra_roll`, `teleport` and `reverse`. Snakes have to end on
+//! a lower tile and ladders on a higher one, no two entries may share a tile
+//! and only a ladder may end on the winning tile.
+
+use std::{fs, io, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::engine::{BOARD_DIMENSIONS, Board, BoardSize, Special, Tile};
+
+/// A board as written in a file, see the [module docs](self).
+#[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BoardFile {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub name: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub author: String,
+    pub width: usize,
+    pub height: usize,
+    #[serde(default)]
+    pub snakes: Vec<Link>,
+    #[serde(default)]
+    pub ladders: Vec<Link>,
+    #[serde(default)]
+    pub safe: Vec<i32>,
+    #[serde(default)]
+    pub specials: Vec<SpecialTile>,
+}
+
+/// A snake or a ladder, by tile number.
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Link {
+    pub from: i32,
+    pub to: i32,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SpecialTile {
+    pub tile: i32,
+    pub effect: Special,
+}
+
+/// Why a [`BoardFile`] is not a playable board. Tiles are numbered as in the
+/// file.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum BoardError {
+    /// The width or height is outside [`BOARD_DIMENSIONS`].
+    Size,
+    /// The tile is not on the board.
+    OutOfRange { tile: i32 },
+    /// More than one entry uses the tile.
+    Overlap { tile: i32 },
+    /// Something other than the end of a ladder is on the winning tile.
+    LastTile,
+    /// A snake that does not go down or a ladder that does not go up.
+    WrongWay { from: i32, to: i32 },
+}
+
+impl std::fmt::Display for BoardError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BoardError::Size => write!(
+                f,
+                "the board must be {} to {} tiles wide and high",
+                BOARD_DIMENSIONS.start(),
+                BOARD_DIMENSIONS.end()
+            ),
+            BoardError::OutOfRange { tile } => write!(f, "tile {tile} is not on the board"),
+            BoardError::Overlap { tile } => write!(f, "tile {tile} is used more than once"),
+            BoardError::LastTile => f.write_str("the last tile must stay free"),
+            BoardError::WrongWay { from, to } => {
+                write!(f, "{from} to {to} goes the wrong way")
+            }
+        }
+    }
+}
+
+impl std::error::Error for BoardError {}
+
+impl TryFrom<BoardFile> for Board {
+    type Error = BoardError;
+
+    fn try_from(file: BoardFile) -> Result<Self, Self::Error> {
+        if !BOARD_DIMENSIONS.contains(&file.width) || !BOARD_DIMENSIONS.contains(&file.height) {
+            return Err(BoardError::Size);
+        }
+        let mut board = Board {
+            name: file.name,
+            author: file.author,
+            ..Board::empty(BoardSize {
+                width: file.width,
+                height: file.height,
+            })
+        };
+        let tiles = board.tiles() as i32;
+        let place = |board: &mut Board, tile: i32, value: Tile| {
+            if !(1..=tiles).contains(&tile) {
+                return Err(BoardError::OutOfRange { tile });
+            }
+            let pos = board.position(tile - 1);
+            if !matches!(board.get(pos), Tile::None) {
+                return Err(BoardError::Overlap { tile });
+            }
+            if tile == tiles && !matches!(value, Tile::Target) {
+                return Err(BoardError::LastTile);
+            }
+            board.tile[pos.y as usize][pos.x as usize] = value;
+            Ok(pos)
+        };
+        for (links, down) in [(&file.snakes, true), (&file.ladders, false)] {
+            for &Link { from, to } in links {
+                let end = place(&mut board, to, Tile::Target)?;
+                let start = if down {
+                    Tile::Snake(end)
+                } else {
+                    Tile::Ladder(end)
+                };
+                place(&mut board, from, start)?;
+                if (down && to >= from) || (!down && to <= from) {
+                    return Err(BoardError::WrongWay { from, to });
+                }
+            }
+        }
+        for &tile in &file.safe {
+            place(&mut board, tile, Tile::Safe)?;
+        }
+        for &SpecialTile { tile, effect } in &file.specials {
+            place(&mut board, tile, Tile::Special(effect))?;
+        }
+        Ok(board)
+    }
+}
+
+impl From<Board> for BoardFile {
+    fn from(board: Board) -> Self {
+        let mut file = BoardFile {
+            width: board.width,
+            height: board.height,
+            ..BoardFile::default()
+        };
+        for index in 0..board.tiles() as i32 {
+            let tile = index + 1;
+            match board.get(board.position(index)) {
+                Tile::Snake(to) => file.snakes.push(Link {
+                    from: tile,
+                    to: board.index(to) + 1,
+                }),
+                Tile::Ladder(to) => file.ladders.push(Link {
+                    from: tile,
+                    to: board.index(to) + 1,
+                }),
+                Tile::Safe => file.safe.push(tile),
+                Tile::Special(effect) => file.specials.push(SpecialTile { tile, effect }),
+                Tile::Target | Tile::None => (),
+            }
+        }
+        file.name = board.name;
+        file.author = board.author;
+        file
+    }
+}
+
+/// Why [`load`] could not read a board.
+#[derive(Debug)]
+pub enum LoadError {
+    Io(io::Error),
+    /// Not a board file, or one failing a [`BoardError`] check.
+    Parse(serde_json::Error),
+}
+
+impl std::fmt::Display for LoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoadError::Io(err) => write!(f, "cannot read the file: {err}"),
+            LoadError::Parse(err) => write!(f, "not a valid board: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+/// Reads the board file at `path`.
+pub fn load(path: impl AsRef<Path>) -> Result<Board, LoadError> {
+    let text = fs::read_to_string(path).map_err(LoadError::Io)?;
+    serde_json::from_str(&text).map_err(LoadError::Parse)
+}
+
+/// Writes `board` to `path` as a board file.
+pub fn save(board: &Board, path: impl AsRef<Path>) -> io::Result<()> {
+    let text = serde_json::to_string_pretty(board).map_err(io::Error::other)?;
+    fs::write(path, text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file() -> BoardFile {
+        BoardFile {
+            name: "Garden".into(),
+            author: "Atharva".into(),
+            width: 5,
+            height: 6,
+            snakes: vec![Link { from: 17, to: 7 }],
+            ladders: vec![Link { from: 4, to: 30 }],
+            safe: vec![12],
+            specials: vec![SpecialTile {
+                tile: 20,
+                effect: Special::Teleport,
+            }],
+        }
+    }
+
+    fn check(edit: impl FnOnce(&mut BoardFile)) -> Result<Board, BoardError> {
+        let mut file = file();
+        edit(&mut file);
+        Board::try_from(file)
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let board = Board::try_from(file()).unwrap();
+        let json = serde_json::to_string(&board).unwrap();
+        let back: Board = serde_json::from_str(&json).unwrap();
+        assert_eq!(BoardFile::from(back), file());
+    }
+
+    #[test]
+    fn rejects_broken_boards() {
+        assert_eq!(check(|f| f.width = 4).unwrap_err(), BoardError::Size);
+        assert_eq!(check(|f| f.height = 21).unwrap_err(), BoardError::Size);
+        assert_eq!(
+            check(|f| f.safe.push(31)).unwrap_err(),
+            BoardError::OutOfRange { tile: 31 }
+        );
+        assert_eq!(
+            check(|f| f.safe.push(0)).unwrap_err(),
+            BoardError::OutOfRange { tile: 0 }
+        );
+        assert_eq!(
+            check(|f| f.safe.push(17)).unwrap_err(),
+            BoardError::Overlap { tile: 17 }
+        );
+        assert_eq!(
+            check(|f| f.safe.push(30)).unwrap_err(),
+            BoardError::Overlap { tile: 30 }
+        );
+        assert_eq!(
+            check(|f| f.ladders[0].to = 1).unwrap_err(),
+            BoardError::WrongWay { from: 4, to: 1 }
+        );
+        assert_eq!(
+            check(|f| f.snakes[0].to = 27).unwrap_err(),
+            BoardError::WrongWay { from: 17, to: 27 }
+        );
+        assert_eq!(
+            check(|f| {
+                f.ladders.clear();
+                f.snakes.push(Link { from: 30, to: 2 });
+            })
+            .unwrap_err(),
+            BoardError::LastTile
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_fields() {
+        let json = r#"{ "width": 5, "height": 5, "portals": [] }"#;
+        assert!(serde_json::from_str::<Board>(json).is_err());
+    }
+}
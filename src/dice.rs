@@ -0,0 +1,206 @@
+//! Where the numbers for each roll come from.
+
+use std::collections::VecDeque;
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::engine::GameRng;
+
+/// How the faces of several dice turn into a move.
+#[derive(Default, Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub enum Combine {
+    /// Move by the total of all dice.
+    #[default]
+    Sum,
+    /// The player picks one die and moves by it.
+    Pick,
+}
+
+impl std::fmt::Display for Combine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Combine::Sum => "Sum",
+            Combine::Pick => "Pick one",
+        })
+    }
+}
+
+/// Number of dice and faces per die a game can be played with.
+pub const DICE_COUNTS: std::ops::RangeInclusive<u8> = 1..=4;
+pub const DICE_SIDES: [u8; 6] = [4, 6, 8, 10, 12, 20];
+
+/// The dice thrown on every roll.
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub struct DiceConfig {
+    pub count: u8,
+    pub sides: u8,
+    pub combine: Combine,
+}
+
+impl Default for DiceConfig {
+    fn default() -> Self {
+        DiceConfig {
+            count: 1,
+            sides: 6,
+            combine: Combine::default(),
+        }
+    }
+}
+
+impl DiceConfig {
+    /// Whether the player has to choose a die before moving.
+    pub fn needs_pick(&self) -> bool {
+        self.combine == Combine::Pick && self.count > 1
+    }
+}
+
+pub trait DiceSource: std::fmt::Debug {
+    /// Faces of the next throw of `dice`, or `None` when the source has
+    /// nothing to give yet (a manual roll that was not entered, or a finished
+    /// script).
+    fn roll(&mut self, dice: &DiceConfig) -> Option<Vec<u8>>;
+
+    /// Whether the players type in the values themselves.
+    fn is_manual(&self) -> bool {
+        false
+    }
+
+    /// Stores the face of one die typed in by a player for the next
+    /// [`roll`](Self::roll). Sources that are not manual ignore it.
+    fn enter(&mut self, _value: u8) {}
+
+    /// Faces entered so far for the next roll.
+    fn entered(&self) -> &[u8] {
+        &[]
+    }
+
+    /// Drops the faces entered for a roll that will not be made.
+    fn clear(&mut self) {}
+
+    /// Generator of seeded dice, saved so a resumed game keeps the same rolls.
+    fn rng(&self) -> Option<&GameRng> {
+        None
+    }
+}
+
+/// Unseeded dice, every game is different.
+#[derive(Debug, Default)]
+pub struct RandomDice;
+
+impl DiceSource for RandomDice {
+    fn roll(&mut self, dice: &DiceConfig) -> Option<Vec<u8>> {
+        let mut rng = rand::thread_rng();
+        Some(
+            (0..dice.count)
+                .map(|_| rng.gen_range(1..=dice.sides))
+                .collect(),
+        )
+    }
+}
+
+/// Dice driven by a [`GameRng`], so a seed replays the same rolls.
+#[derive(Debug)]
+pub struct SeededDice {
+    rng: GameRng,
+}
+
+impl SeededDice {
+    pub fn new(rng: GameRng) -> Self {
+        SeededDice { rng }
+    }
+}
+
+impl DiceSource for SeededDice {
+    fn roll(&mut self, dice: &DiceConfig) -> Option<Vec<u8>> {
+        Some(
+            (0..dice.count)
+                .map(|_| self.rng.gen_range(1..=dice.sides))
+                .collect(),
+        )
+    }
+
+    fn rng(&self) -> Option<&GameRng> {
+        Some(&self.rng)
+    }
+}
+
+/// Fixed sequence of faces, mainly for tests and bots. Each roll takes as
+/// many faces as there are dice.
+#[derive(Debug, Default)]
+pub struct ScriptedDice {
+    rolls: VecDeque<u8>,
+}
+
+impl ScriptedDice {
+    pub fn new(rolls: impl IntoIterator<Item = u8>) -> Self {
+        ScriptedDice {
+            rolls: rolls.into_iter().collect(),
+        }
+    }
+}
+
+impl DiceSource for ScriptedDice {
+    fn roll(&mut self, dice: &DiceConfig) -> Option<Vec<u8>> {
+        let count = dice.count as usize;
+        (self.rolls.len() >= count).then(|| self.rolls.drain(..count).collect())
+    }
+}
+
+/// Physical dice: the players roll at the table and enter each face.
+#[derive(Debug, Default)]
+pub struct ManualDice {
+    pending: Vec<u8>,
+}
+
+impl DiceSource for ManualDice {
+    fn roll(&mut self, dice: &DiceConfig) -> Option<Vec<u8>> {
+        (self.pending.len() >= dice.count as usize).then(|| std::mem::take(&mut self.pending))
+    }
+
+    fn is_manual(&self) -> bool {
+        true
+    }
+
+    fn enter(&mut self, value: u8) {
+        self.pending.push(value);
+    }
+
+    fn entered(&self) -> &[u8] {
+        &self.pending
+    }
+
+    fn clear(&mut self) {
+        self.pending.clear();
+    }
+}
+
+/// Dice sources that can be picked from the menu.
+#[derive(Default, Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub enum DiceKind {
+    #[default]
+    Seeded,
+    Random,
+    Manual,
+}
+
+impl DiceKind {
+    /// Builds the source, `rng` is only used by [`DiceKind::Seeded`].
+    pub fn source(self, rng: GameRng) -> Box<dyn DiceSource> {
+        match self {
+            DiceKind::Seeded => Box::new(SeededDice::new(rng)),
+            DiceKind::Random => Box::new(RandomDice),
+            DiceKind::Manual => Box::new(ManualDice::default()),
+        }
+    }
+}
+
+impl std::fmt::Display for DiceKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            DiceKind::Seeded => "Seeded",
+            DiceKind::Random => "Random",
+            DiceKind::Manual => "Manual",
+        })
+    }
+}
@@ -0,0 +1,1761 @@
+//! Headless rules of the game.
+//!
+//! Nothing in here depends on iced, so the same [`GameState`] can be driven
+//! by the desktop app, by bots or by simulations.
+
+use std::ops::RangeInclusive;
+
+use rand::{Rng, SeedableRng, seq::SliceRandom};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    analysis,
+    board_file::BoardFile,
+    dice::{DiceConfig, DiceSource},
+};
+
+/// Generator used for everything random in a game. It is portable, so the
+/// same seed gives the same board and dice on every machine.
+pub type GameRng = ChaCha8Rng;
+
+/// Generators for the board layout, the dice and the tile effects of the game
+/// started with `seed`. They use separate streams, so the dice sequence does
+/// not depend on how many numbers the board generation consumed.
+pub fn seeded_rngs(seed: u64) -> (GameRng, GameRng, GameRng) {
+    let board_rng = GameRng::seed_from_u64(seed);
+    let mut dice_rng = GameRng::seed_from_u64(seed);
+    dice_rng.set_stream(1);
+    let mut effect_rng = GameRng::seed_from_u64(seed);
+    effect_rng.set_stream(2);
+    (board_rng, dice_rng, effect_rng)
+}
+
+#[derive(Default, Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub enum Mode {
+    #[default]
+    Friendly,
+    Bump,
+    Swap,
+}
+
+impl std::fmt::Display for Mode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Mode::Friendly => "Friendly",
+            Mode::Bump => "Bump",
+            Mode::Swap => "Swap",
+        })
+    }
+}
+
+/// What happens when a roll would move a player past the last tile.
+#[derive(Default, Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub enum OvershootRule {
+    /// The exact number is needed, otherwise the turn is wasted. When several
+    /// dice are summed a single die showing the exact number is enough, as
+    /// the sum alone could never reach the last tiles.
+    #[default]
+    Exact,
+    /// The player walks back by the excess.
+    Bounce,
+    /// Any roll reaching or passing the last tile wins.
+    Pass,
+}
+
+impl std::fmt::Display for OvershootRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            OvershootRule::Exact => "Exact roll",
+            OvershootRule::Bounce => "Bounce back",
+            OvershootRule::Pass => "Pass to win",
+        })
+    }
+}
+
+/// Which throws let the player roll again.
+#[derive(Default, Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub enum ExtraTurnRule {
+    Off,
+    /// Any die shows its highest face, a 6 on normal dice.
+    #[default]
+    Six,
+    /// All dice show the same face, needs at least two dice.
+    Doubles,
+}
+
+impl ExtraTurnRule {
+    pub fn grants(&self, dice: &[u8], sides: u8) -> bool {
+        match self {
+            ExtraTurnRule::Off => false,
+            ExtraTurnRule::Six => dice.contains(&sides),
+            ExtraTurnRule::Doubles => dice.len() > 1 && dice.iter().all(|&d| d == dice[0]),
+        }
+    }
+}
+
+impl std::fmt::Display for ExtraTurnRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ExtraTurnRule::Off => "Off",
+            ExtraTurnRule::Six => "On a 6",
+            ExtraTurnRule::Doubles => "On doubles",
+        })
+    }
+}
+
+/// Penalty for earning a third extra roll in a single turn, three sixes with
+/// the classic rules.
+#[derive(Default, Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub enum ThreeSixesRule {
+    /// Keep rolling as long as sixes come up.
+    #[default]
+    Off,
+    /// The third roll is not moved and the turn ends.
+    Forfeit,
+    /// The player goes back to the tile the turn started on.
+    BackToStart,
+}
+
+impl std::fmt::Display for ThreeSixesRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ThreeSixesRule::Off => "No penalty",
+            ThreeSixesRule::Forfeit => "Forfeit the move",
+            ThreeSixesRule::BackToStart => "Back to start of turn",
+        })
+    }
+}
+
+/// When a team counts as finished.
+#[derive(Default, Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub enum TeamWin {
+    /// As soon as one member reaches the last tile.
+    #[default]
+    Any,
+    /// Once every member reached the last tile.
+    All,
+}
+
+impl std::fmt::Display for TeamWin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            TeamWin::Any => "Any member",
+            TeamWin::All => "All members",
+        })
+    }
+}
+
+/// House rules a game is played with.
+#[derive(Default, Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub struct Rules {
+    pub mode: Mode,
+    pub overshoot: OvershootRule,
+    pub dice: DiceConfig,
+    pub extra_turn: ExtraTurnRule,
+    pub three_sixes: ThreeSixesRule,
+    /// Roll needed to enter the board, players start off the board when set.
+    pub entry_roll: Option<u8>,
+    /// Team play, players are grouped by [`Player::team`] when set.
+    pub teams: Option<TeamWin>,
+    /// Hand out power-up [`Card`]s on a roll showing the highest face and on
+    /// special tiles.
+    pub cards: bool,
+}
+
+/// Power-up a player keeps until they play it.
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub enum Card {
+    /// Ignores the next snake the player lands on.
+    SnakeShield,
+    /// Adds two tiles to the next move.
+    PlusTwo,
+    /// Takes back the last roll of the player, so they can roll again.
+    ReRoll,
+    /// Trades places with the player furthest ahead.
+    SwapWithLeader,
+}
+
+impl Card {
+    pub const ALL: [Card; 4] = [
+        Card::SnakeShield,
+        Card::PlusTwo,
+        Card::ReRoll,
+        Card::SwapWithLeader,
+    ];
+}
+
+impl std::fmt::Display for Card {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Card::SnakeShield => "Snake shield",
+            Card::PlusTwo => "+2 move",
+            Card::ReRoll => "Re-roll",
+            Card::SwapWithLeader => "Swap with leader",
+        })
+    }
+}
+
+/// Most cards a player can hold, no more are handed out past it.
+pub const MAX_CARDS: usize = 3;
+
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub struct Position {
+    pub x: i32,
+    pub y: i32,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Tile {
+    Snake(Position),
+    Ladder(Position),
+    Target,
+    /// Tokens can share the tile, bump and swap do not apply here.
+    Safe,
+    Special(Special),
+    None,
+}
+
+/// Party tiles that do something to the player landing on them.
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Special {
+    /// The player loses their next turn.
+    SkipTurn,
+    /// The player rolls again.
+    ExtraRoll,
+    /// The player jumps to a random free tile.
+    Teleport,
+    /// The next roll of the player moves them backwards.
+    Reverse,
+}
+
+impl Special {
+    pub const ALL: [Special; 4] = [
+        Special::SkipTurn,
+        Special::ExtraRoll,
+        Special::Teleport,
+        Special::Reverse,
+    ];
+}
+
+/// Widths and heights a board can have.
+pub const BOARD_DIMENSIONS: RangeInclusive<usize> = 5..=20;
+
+/// Number of columns and rows of a board.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct BoardSize {
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Default for BoardSize {
+    fn default() -> Self {
+        BoardSize {
+            width: 10,
+            height: 10,
+        }
+    }
+}
+
+impl BoardSize {
+    /// Furthest zero based tile a [`Handicap`] can start a player on, a
+    /// fifth of the way up the board.
+    pub fn max_head_start(&self) -> i32 {
+        (self.width * self.height / 5).max(1) as i32 - 1
+    }
+}
+
+impl std::fmt::Display for BoardSize {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}x{}", self.width, self.height)
+    }
+}
+
+/// Tiles are numbered from the bottom left corner and snake back and forth,
+/// so `tile[y][x]` is row `y` counted from the bottom. Boards are saved in
+/// the format described in [`board_file`](crate::board_file).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "BoardFile", into = "BoardFile")]
+pub struct Board {
+    pub width: usize,
+    pub height: usize,
+    pub tile: Vec<Vec<Tile>>,
+    /// Empty for generated boards.
+    pub name: String,
+    pub author: String,
+}
+
+/// What a generated board has to look like.
+#[derive(Debug, PartialEq, Clone)]
+pub struct BoardSpec {
+    pub size: BoardSize,
+    pub snakes: usize,
+    pub ladders: usize,
+    /// Shortest snake or ladder, in tiles. Either way they always end on
+    /// another row than they start.
+    pub min_length: i32,
+    /// Longest snake or ladder, in tiles.
+    pub max_length: i32,
+    /// Zero based tiles no snake or ladder may start or end on.
+    pub banned: Vec<i32>,
+    /// Safe tiles to add once the snakes and ladders are placed.
+    pub safe: usize,
+    /// Percent of the tiles that get one of the [`Special`] effects.
+    pub specials: u8,
+}
+
+impl BoardSpec {
+    /// As many snakes and ladders per tile as the classic 10x10 board has,
+    /// of any length, and a few safe tiles. The first and the last tile are
+    /// kept free.
+    pub fn new(size: BoardSize) -> Self {
+        let tiles = size.width * size.height;
+        BoardSpec {
+            size,
+            snakes: (tiles * 8 / 100).max(1),
+            ladders: (tiles * 8 / 100).max(1),
+            min_length: 1,
+            max_length: tiles as i32,
+            banned: vec![0, tiles as i32 - 1],
+            safe: (tiles * 4 / 100).max(1),
+            specials: 0,
+        }
+    }
+}
+
+/// How hard a board from [`Board::new`] plays.
+#[derive(Default, Debug, PartialEq, Clone, Copy)]
+pub enum Difficulty {
+    /// Short games, few snakes and plenty of ladders.
+    Easy,
+    /// About as long as the same board without snakes or ladders.
+    #[default]
+    Normal,
+    /// Long games with many snakes.
+    Hard,
+    /// Crowded with snakes and ladders, the game can go either way.
+    Chaos,
+}
+
+impl std::fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Difficulty::Easy => "Easy",
+            Difficulty::Normal => "Normal",
+            Difficulty::Hard => "Hard",
+            Difficulty::Chaos => "Chaos",
+        })
+    }
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 4] = [
+        Difficulty::Easy,
+        Difficulty::Normal,
+        Difficulty::Hard,
+        Difficulty::Chaos,
+    ];
+
+    /// Spec for the candidate boards, snakes and ladders per hundred tiles
+    /// set by the difficulty. Easy keeps at least five ladders, as the two or
+    /// three a small board would get cannot shorten the game into its band.
+    pub fn spec(self, size: BoardSize) -> BoardSpec {
+        let (snakes, ladders, min_ladders) = match self {
+            Difficulty::Easy => (4, 10, 5),
+            Difficulty::Normal => (8, 8, 1),
+            Difficulty::Hard => (12, 6, 1),
+            Difficulty::Chaos => (14, 14, 1),
+        };
+        let tiles = size.width * size.height;
+        BoardSpec {
+            snakes: (tiles * snakes / 100).max(1),
+            ladders: (tiles * ladders / 100).max(min_ladders),
+            ..BoardSpec::new(size)
+        }
+    }
+
+    /// Expected rolls to finish, relative to the same board without snakes
+    /// or ladders.
+    pub fn length_band(self) -> RangeInclusive<f64> {
+        match self {
+            Difficulty::Easy => 0.5..=0.8,
+            Difficulty::Normal => 0.85..=1.15,
+            Difficulty::Hard => 1.3..=2.2,
+            Difficulty::Chaos => 0.5..=2.5,
+        }
+    }
+
+    /// Snakes slid down per roll.
+    pub fn snake_band(self) -> RangeInclusive<f64> {
+        match self {
+            Difficulty::Easy => 0.0..=0.05,
+            Difficulty::Normal => 0.06..=0.1,
+            Difficulty::Hard => 0.09..=0.15,
+            Difficulty::Chaos => 0.11..=1.0,
+        }
+    }
+
+    /// How far a board is outside both bands, each measured in band widths.
+    /// Zero when it is inside.
+    fn miss(self, length: f64, snake_rate: f64) -> f64 {
+        fn outside(band: RangeInclusive<f64>, value: f64) -> f64 {
+            let width = band.end() - band.start();
+            (band.start() - value).max(value - band.end()).max(0.0) / width
+        }
+        outside(self.length_band(), length) + outside(self.snake_band(), snake_rate)
+    }
+}
+
+/// Boards [`Board::new`] tries before settling for the closest one.
+const CANDIDATES: usize = 12;
+
+/// Why [`Board::generate`] could not build a board.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum GenerationError {
+    /// The board needs at least two rows and two columns.
+    TooSmall,
+    /// `min_length` is below 1 or above `max_length`.
+    BadLengths,
+    /// Only `placed` snakes fit within the constraints.
+    TooManySnakes { placed: usize },
+    /// Only `placed` ladders fit within the constraints.
+    TooManyLadders { placed: usize },
+}
+
+impl std::fmt::Display for GenerationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GenerationError::TooSmall => f.write_str("the board is too small"),
+            GenerationError::BadLengths => f.write_str("the length range is empty"),
+            GenerationError::TooManySnakes { placed } => {
+                write!(f, "only {placed} snakes fit on the board")
+            }
+            GenerationError::TooManyLadders { placed } => {
+                write!(f, "only {placed} ladders fit on the board")
+            }
+        }
+    }
+}
+
+impl std::error::Error for GenerationError {}
+
+impl Board {
+    /// Places up to `num` snakes (`down`) or ladders and returns how many fit.
+    /// Start tiles are tried in random order and every end tile is checked
+    /// for each of them, so this gives up once nothing fits instead of
+    /// retrying forever.
+    fn add_snl(&mut self, rng: &mut impl Rng, spec: &BoardSpec, num: usize, down: bool) -> usize {
+        for placed in 0..num {
+            let mut starts: Vec<i32> = (0..self.tiles() as i32)
+                .filter(|&i| self.free_for_snl(spec, i))
+                .collect();
+            starts.shuffle(rng);
+            let found = starts.into_iter().find_map(|start| {
+                let ends: Vec<i32> = (0..self.tiles() as i32)
+                    .filter(|&end| {
+                        self.free_for_snl(spec, end) && self.snl_fits(spec, start, end, down)
+                    })
+                    .collect();
+                ends.choose(rng).map(|&end| (start, end))
+            });
+            let Some((start, end)) = found else {
+                return placed;
+            };
+            let (start, end) = (self.position(start), self.position(end));
+            self.tile[start.y as usize][start.x as usize] = if down {
+                Tile::Snake(end)
+            } else {
+                Tile::Ladder(end)
+            };
+            self.tile[end.y as usize][end.x as usize] = Tile::Target;
+        }
+        num
+    }
+
+    fn free_for_snl(&self, spec: &BoardSpec, index: i32) -> bool {
+        !spec.banned.contains(&index) && matches!(self.get(self.position(index)), Tile::None)
+    }
+
+    /// Whether a snake (`down`) or ladder can go from `start` to `end`.
+    fn snl_fits(&self, spec: &BoardSpec, start: i32, end: i32, down: bool) -> bool {
+        let (from, to) = (self.position(start), self.position(end));
+        let length = (end - start).abs();
+        if (down && to.y >= from.y)
+            || (!down && to.y <= from.y)
+            || !(spec.min_length..=spec.max_length).contains(&length)
+        {
+            return false;
+        }
+        // no snake head right above the foot of a ladder
+        if down {
+            self.below(from)
+                .is_none_or(|pos| !matches!(self.get(pos), Tile::Ladder(_)))
+        } else {
+            self.above(from)
+                .is_none_or(|pos| !matches!(self.get(pos), Tile::Snake(_)))
+        }
+    }
+
+    /// Turns `num` random empty tiles into `tile`, leaving the first and the
+    /// last tile alone. Places fewer when the board runs out of empty tiles.
+    fn add_tiles(&mut self, rng: &mut impl Rng, num: usize, tile: Tile) {
+        let free: Vec<i32> = (1..self.last())
+            .filter(|&i| matches!(self.get(self.position(i)), Tile::None))
+            .collect();
+        for &i in free.choose_multiple(rng, num) {
+            let pos = self.position(i);
+            self.tile[pos.y as usize][pos.x as usize] = tile;
+        }
+    }
+
+    /// Generates a random board following `spec`. Safe and special tiles go
+    /// on whatever room is left, fewer of them are placed when it runs out.
+    pub fn generate(spec: &BoardSpec, rng: &mut impl Rng) -> Result<Self, GenerationError> {
+        let BoardSize { width, height } = spec.size;
+        if width < 2 || height < 2 {
+            return Err(GenerationError::TooSmall);
+        }
+        if spec.min_length < 1 || spec.min_length > spec.max_length {
+            return Err(GenerationError::BadLengths);
+        }
+        let mut board = Board::empty(spec.size);
+        let placed = board.add_snl(rng, spec, spec.snakes, true);
+        if placed < spec.snakes {
+            return Err(GenerationError::TooManySnakes { placed });
+        }
+        let placed = board.add_snl(rng, spec, spec.ladders, false);
+        if placed < spec.ladders {
+            return Err(GenerationError::TooManyLadders { placed });
+        }
+        board.add_tiles(rng, spec.safe, Tile::Safe);
+        let num = board.tiles() * spec.specials as usize / 100;
+        for (i, special) in Special::ALL.into_iter().enumerate() {
+            let share = num / Special::ALL.len() + usize::from(i < num % Special::ALL.len());
+            board.add_tiles(rng, share, Tile::Special(special));
+        }
+        Ok(board)
+    }
+
+    /// A board without snakes, ladders or special tiles.
+    pub fn empty(size: BoardSize) -> Self {
+        Board {
+            width: size.width,
+            height: size.height,
+            tile: vec![vec![Tile::None; size.width]; size.height],
+            name: String::new(),
+            author: String::new(),
+        }
+    }
+
+    /// Generates a random board that plays like `difficulty` under the rules
+    /// of `throws`, `specials` percent of the tiles get one of the
+    /// [`Special`] effects. Candidates from [`Difficulty::spec`] are analysed
+    /// until one falls inside the bands of the difficulty, the closest is
+    /// kept when none does. The analysis leaves out extra turns from the dice
+    /// to stay quick on big boards.
+    ///
+    /// # Panics
+    ///
+    /// On boards too small for the spec such as 2x2, boards from 5x5 up
+    /// always have room.
+    pub fn new(
+        size: BoardSize,
+        specials: u8,
+        difficulty: Difficulty,
+        throws: &analysis::Throws,
+        rng: &mut impl Rng,
+    ) -> Self {
+        let spec = BoardSpec {
+            specials,
+            ..difficulty.spec(size)
+        };
+        // candidates are compared with a single roll per turn, which ranks
+        // them about the same for a fraction of the cost of following every
+        // extra roll
+        let throws = throws.single_rolls();
+        let plain = analysis::analyze_with(&Board::empty(size), &throws).expected_rolls;
+        let mut best: Option<(f64, Board)> = None;
+        for _ in 0..CANDIDATES {
+            let board = Self::generate(&spec, rng).expect("the spec fits every board");
+            let odds = analysis::analyze_with(&board, &throws);
+            let miss = difficulty.miss(odds.expected_rolls / plain, odds.snake_rate());
+            if miss == 0.0 {
+                return board;
+            }
+            if best.as_ref().is_none_or(|(closest, _)| miss < *closest) {
+                best = Some((miss, board));
+            }
+        }
+        best.expect("at least one candidate").1
+    }
+
+    pub fn size(&self) -> BoardSize {
+        BoardSize {
+            width: self.width,
+            height: self.height,
+        }
+    }
+
+    pub fn tiles(&self) -> usize {
+        self.width * self.height
+    }
+
+    /// Zero based index of the winning tile.
+    pub fn last(&self) -> i32 {
+        self.tiles() as i32 - 1
+    }
+
+    /// Builds the position of the zero based tile `index`.
+    pub fn position(&self, index: i32) -> Position {
+        Position {
+            x: index % self.width as i32,
+            y: index / self.width as i32,
+        }
+    }
+
+    /// Zero based tile index, tile 1 on the board is index 0.
+    pub fn index(&self, pos: Position) -> i32 {
+        pos.y * self.width as i32 + pos.x
+    }
+
+    pub fn get(&self, pos: Position) -> Tile {
+        self.tile[pos.y as usize][pos.x as usize]
+    }
+
+    /// Where a player with a head start to the zero based tile `start`
+    /// begins. The start is capped at [`BoardSize::max_head_start`] and a
+    /// snake or ladder on it is taken, unless it leads to the last tile.
+    /// Without a head start players stand on the first tile as it is, like
+    /// after a bump.
+    pub fn start_position(&self, start: i32) -> Position {
+        let pos = self.position(start.clamp(0, self.size().max_head_start()));
+        match self.get(pos) {
+            Tile::Snake(to) | Tile::Ladder(to) if start > 0 && self.index(to) != self.last() => to,
+            _ => pos,
+        }
+    }
+
+    /// Tile drawn right above `pos`, rows run in alternating directions.
+    pub fn above(&self, pos: Position) -> Option<Position> {
+        (pos.y + 1 < self.height as i32).then(|| Position {
+            x: self.width as i32 - 1 - pos.x,
+            y: pos.y + 1,
+        })
+    }
+
+    /// Tile drawn right below `pos`.
+    pub fn below(&self, pos: Position) -> Option<Position> {
+        (pos.y > 0).then(|| Position {
+            x: self.width as i32 - 1 - pos.x,
+            y: pos.y - 1,
+        })
+    }
+}
+
+/// Head start to even out a game between players of different ages.
+#[derive(Default, Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub struct Handicap {
+    /// Zero based tile the player starts on, see [`Board::start_position`].
+    pub start: i32,
+    /// Tiles added to every move.
+    pub dice_bonus: u8,
+    /// Snakes the player can still land on without sliding down.
+    pub snake_immunities: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Player {
+    pub name: String,
+    pub position: Position,
+    /// False while waiting for the entry roll, `position` is meaningless then.
+    pub on_board: bool,
+    /// Turns taken so far, a turn with extra rolls counts once.
+    pub turns: u32,
+    /// Finishing place, 1 for the winner.
+    pub place: Option<usize>,
+    /// Team the player belongs to, only used with [`Rules::teams`].
+    pub team: usize,
+    /// Landed on [`Special::SkipTurn`], the next turn is passed over.
+    pub skip_next: bool,
+    /// Landed on [`Special::Reverse`], the next move goes backwards.
+    pub reverse_next: bool,
+    /// Cards in hand, see [`Rules::cards`].
+    pub cards: Vec<Card>,
+    /// A [`Card::SnakeShield`] was played and no snake hit yet.
+    pub shield: bool,
+    /// Tiles added to the next move by [`Card::PlusTwo`].
+    pub bonus: u8,
+    pub handicap: Handicap,
+}
+
+impl Player {
+    pub fn new(name: impl Into<String>) -> Self {
+        Player {
+            name: name.into(),
+            position: Position { x: 0, y: 0 },
+            on_board: true,
+            turns: 0,
+            place: None,
+            team: 0,
+            skip_next: false,
+            reverse_next: false,
+            cards: Vec::new(),
+            shield: false,
+            bonus: 0,
+            handicap: Handicap::default(),
+        }
+    }
+
+    pub fn finished(&self) -> bool {
+        self.place.is_some()
+    }
+}
+
+/// Something that happened while a roll was applied.
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub enum Event {
+    /// The roll would have gone past the last tile, so the player stays put.
+    Overshoot,
+    /// The roll went past the last tile and the player walked back.
+    Bounced,
+    Snake {
+        from: Position,
+        to: Position,
+    },
+    Ladder {
+        from: Position,
+        to: Position,
+    },
+    /// The player rolled the entry roll and is placed on the board, on their
+    /// head start tile until they are first bumped and on the first tile
+    /// after that.
+    Entered,
+    /// The player is off the board and did not roll the entry roll.
+    NotEntered,
+    /// Another player was sent back to the start.
+    Bumped(usize),
+    /// Another player was moved to the tile the current player came from.
+    Swapped(usize),
+    /// The player reached the last tile in the given place.
+    Finished(usize),
+    /// The team of the player is done, in the given place.
+    TeamFinished {
+        team: usize,
+        place: usize,
+    },
+    /// Third extra roll of the turn, penalised according to [`ThreeSixesRule`].
+    ThreeSixes,
+    /// The player landed on a special tile, `to` of the outcome already
+    /// includes a teleport.
+    Special(Special),
+    /// The player moved backwards because of [`Special::Reverse`].
+    Reversed,
+    /// The given player loses this turn because of [`Special::SkipTurn`].
+    Skipped(usize),
+    /// The player got a card.
+    CardEarned(Card),
+    /// The player used a card.
+    CardPlayed(Card),
+    /// A snake was ignored thanks to [`Card::SnakeShield`] or a snake
+    /// immunity of the [`Handicap`].
+    Shielded,
+}
+
+/// Result of a single [`GameState::apply_roll`].
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct TurnOutcome {
+    /// Index of the player who rolled.
+    pub player: usize,
+    /// Faces of every die thrown.
+    pub dice: Vec<u8>,
+    /// Number of tiles the roll is worth.
+    pub roll: u8,
+    pub from: Position,
+    pub to: Position,
+    pub events: Vec<Event>,
+    /// Whether the same player rolls again.
+    pub extra_turn: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameState {
+    pub board: Board,
+    pub players: Vec<Player>,
+    pub rules: Rules,
+    pub player_turn: usize,
+    /// Values of the rolls made so far in the current player's turn.
+    pub turn_rolls: Vec<u8>,
+    /// Rolls of the current turn that earned an extra turn by the dice.
+    pub turn_sixes: usize,
+    /// Where the current player stood when the turn started, `None` when
+    /// they were off the board.
+    pub turn_start: Option<Position>,
+    /// Teams that are done, in finishing order.
+    pub team_places: Vec<usize>,
+    /// Set once every place has been decided.
+    pub ended: bool,
+    /// Picks teleport destinations and cards.
+    pub rng: GameRng,
+    /// The game as it was before the last roll, kept for [`Card::ReRoll`]
+    /// when cards are on.
+    pub before_roll: Option<Box<GameState>>,
+}
+
+impl GameState {
+    /// A game about to start, with every player on their head start tile.
+    /// `rng` picks teleport destinations and cards, pass the third generator
+    /// of [`seeded_rngs`] to replay a seeded game.
+    pub fn new(board: Board, mut players: Vec<Player>, rules: Rules, rng: GameRng) -> Self {
+        for p in players.iter_mut() {
+            p.position = board.start_position(p.handicap.start);
+            p.on_board = rules.entry_roll.is_none();
+        }
+        GameState {
+            board,
+            players,
+            rules,
+            player_turn: 0,
+            turn_rolls: Vec::new(),
+            turn_sixes: 0,
+            turn_start: None,
+            team_places: Vec::new(),
+            ended: false,
+            rng,
+            before_roll: None,
+        }
+    }
+
+    /// Takes the next roll from `dice` and applies it, returns `None` when the
+    /// game is over or the source has no roll to give.
+    pub fn play_turn(&mut self, dice: &mut dyn DiceSource) -> Option<TurnOutcome> {
+        if self.ended {
+            return None;
+        }
+        let faces = dice.roll(&self.rules.dice)?;
+        let pick = self.auto_pick(&faces);
+        Some(self.apply_dice(&faces, pick))
+    }
+
+    /// Die to move by when there is no player to ask: the highest one, or
+    /// `None` when the rules do not let players pick.
+    pub fn auto_pick(&self, faces: &[u8]) -> Option<usize> {
+        self.rules.dice.needs_pick().then(|| {
+            (0..faces.len())
+                .max_by_key(|&i| faces[i])
+                .unwrap_or_default()
+        })
+    }
+
+    /// What a throw of `faces` is worth, moving by die `pick` when the rules
+    /// let the player choose.
+    pub fn roll_value(&self, faces: &[u8], pick: Option<usize>) -> u8 {
+        match pick {
+            Some(i) if self.rules.dice.needs_pick() => faces[i],
+            _ => faces.iter().sum(),
+        }
+    }
+
+    /// Distinct teams of the players, in order of first appearance.
+    pub fn teams(&self) -> Vec<usize> {
+        let mut teams = Vec::new();
+        for p in self.players.iter() {
+            if !teams.contains(&p.team) {
+                teams.push(p.team);
+            }
+        }
+        teams
+    }
+
+    /// Indices of the players in `team`.
+    pub fn team_members(&self, team: usize) -> impl Iterator<Item = usize> + '_ {
+        (0..self.players.len()).filter(move |&i| self.players[i].team == team)
+    }
+
+    /// Finishing place of `team`, 1 for the winning team.
+    pub fn team_place(&self, team: usize) -> Option<usize> {
+        self.team_places
+            .iter()
+            .position(|&t| t == team)
+            .map(|i| i + 1)
+    }
+
+    /// Sum of the tile numbers the members of `team` reached.
+    pub fn team_score(&self, team: usize) -> i32 {
+        self.team_members(team)
+            .filter(|&i| self.players[i].on_board)
+            .map(|i| self.board.index(self.players[i].position) + 1)
+            .sum()
+    }
+
+    fn teammates(&self, a: usize, b: usize) -> bool {
+        self.rules.teams.is_some() && self.players[a].team == self.players[b].team
+    }
+
+    /// Whether `player` no longer takes turns, because they reached the last
+    /// tile or their team is already done.
+    pub fn is_out(&self, player: usize) -> bool {
+        self.players[player].finished()
+            || (self.rules.teams.is_some() && self.team_place(self.players[player].team).is_some())
+    }
+
+    /// Players that have reached the last tile, in finishing order.
+    pub fn finish_order(&self) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..self.players.len())
+            .filter(|&i| self.players[i].finished())
+            .collect();
+        order.sort_by_key(|&i| self.players[i].place);
+        order
+    }
+
+    /// What [`apply_dice`](Self::apply_dice) would do, without changing the
+    /// game. Used to show the player where each choice leads.
+    pub fn preview_dice(&self, faces: &[u8], pick: Option<usize>) -> TurnOutcome {
+        self.clone().apply_dice(faces, pick)
+    }
+
+    /// Moves the current player by a single die showing `roll`.
+    pub fn apply_roll(&mut self, roll: u8) -> TurnOutcome {
+        self.apply_dice(&[roll], None)
+    }
+
+    /// Moves the current player by the throw `faces`, using die `pick` when
+    /// the rules let the player choose, and passes the turn on.
+    ///
+    /// Once the game has ended this is a no-op that reports an unchanged
+    /// position for the player whose turn it would have been.
+    pub fn apply_dice(&mut self, faces: &[u8], pick: Option<usize>) -> TurnOutcome {
+        let player = self.player_turn;
+        let from = self.players[player].position;
+        let roll = self.roll_value(faces, pick);
+        let mut outcome = TurnOutcome {
+            player,
+            dice: faces.to_vec(),
+            roll,
+            from,
+            to: from,
+            events: Vec::new(),
+            extra_turn: false,
+        };
+        if self.ended {
+            return outcome;
+        }
+        if self.rules.cards {
+            self.before_roll = None;
+            self.before_roll = Some(Box::new(self.clone()));
+        }
+
+        if self.turn_rolls.is_empty() {
+            self.turn_start = Some(from).filter(|_| self.players[player].on_board);
+        }
+        self.turn_rolls.push(roll);
+        let six = self.rules.extra_turn.grants(faces, self.rules.dice.sides);
+        self.turn_sixes += usize::from(six);
+
+        if six && self.turn_sixes >= 3 && self.rules.three_sixes != ThreeSixesRule::Off {
+            outcome.events.push(Event::ThreeSixes);
+            if self.rules.three_sixes == ThreeSixesRule::BackToStart {
+                let p = &mut self.players[player];
+                p.on_board = self.turn_start.is_some();
+                p.position = self.turn_start.unwrap_or(Position { x: 0, y: 0 });
+                outcome.to = p.position;
+            }
+        } else if !self.players[player].on_board {
+            if self.rules.entry_roll.is_some_and(|e| faces.contains(&e)) {
+                self.players[player].on_board = true;
+                outcome.events.push(Event::Entered);
+                outcome.extra_turn = six;
+            } else {
+                outcome.events.push(Event::NotEntered);
+            }
+        } else {
+            outcome.extra_turn = six;
+            self.move_player(player, roll, &mut outcome);
+        }
+
+        let earned = faces.contains(&self.rules.dice.sides)
+            || outcome
+                .events
+                .iter()
+                .any(|e| matches!(e, Event::Special(_)));
+        if self.rules.cards && earned && self.players[player].cards.len() < MAX_CARDS {
+            let card = Card::ALL[self.rng.gen_range(0..Card::ALL.len())];
+            self.players[player].cards.push(card);
+            outcome.events.push(Event::CardEarned(card));
+        }
+
+        if self.board.index(self.players[player].position) == self.board.last() {
+            self.finish(player);
+            outcome.events.push(Event::Finished(
+                self.players[player].place.unwrap_or_default(),
+            ));
+            let team = self.players[player].team;
+            if let Some(place) = self.team_place(team) {
+                outcome.events.push(Event::TeamFinished { team, place });
+            }
+            outcome.extra_turn = false;
+        }
+        if !outcome.extra_turn {
+            self.players[player].turns += 1;
+            self.turn_rolls.clear();
+            self.turn_sixes = 0;
+            self.next_turn(&mut outcome);
+        }
+        outcome
+    }
+
+    fn move_player(&mut self, player: usize, roll: u8, outcome: &mut TurnOutcome) {
+        let from = outcome.from;
+        let last = self.board.last();
+        let steps = i32::from(roll)
+            + i32::from(std::mem::take(&mut self.players[player].bonus))
+            + i32::from(self.players[player].handicap.dice_bonus);
+        let mut new_pos = self.board.index(from) + steps;
+        if self.players[player].reverse_next {
+            self.players[player].reverse_next = false;
+            new_pos = (self.board.index(from) - steps).max(0);
+            outcome.events.push(Event::Reversed);
+        } else if new_pos > last {
+            match self.rules.overshoot {
+                OvershootRule::Exact => {
+                    // the face a single die needs, bonuses still apply
+                    let single = last - self.board.index(from) - (steps - i32::from(roll));
+                    let summed = !self.rules.dice.needs_pick() && outcome.dice.len() > 1;
+                    if !summed || !outcome.dice.iter().any(|&d| i32::from(d) == single) {
+                        outcome.events.push(Event::Overshoot);
+                        return;
+                    }
+                    new_pos = last;
+                }
+                OvershootRule::Bounce => {
+                    new_pos = (last - (new_pos - last)).max(0);
+                    outcome.events.push(Event::Bounced);
+                }
+                OvershootRule::Pass => new_pos = last,
+            }
+        }
+
+        let landed = self.board.position(new_pos);
+        let to = match self.board.get(landed) {
+            Tile::Snake(_) if self.players[player].shield => {
+                self.players[player].shield = false;
+                outcome.events.push(Event::Shielded);
+                landed
+            }
+            Tile::Snake(_) if self.players[player].handicap.snake_immunities > 0 => {
+                self.players[player].handicap.snake_immunities -= 1;
+                outcome.events.push(Event::Shielded);
+                landed
+            }
+            Tile::Snake(to) => {
+                outcome.events.push(Event::Snake { from: landed, to });
+                to
+            }
+            Tile::Ladder(to) => {
+                outcome.events.push(Event::Ladder { from: landed, to });
+                to
+            }
+            Tile::Special(special) => {
+                outcome.events.push(Event::Special(special));
+                match special {
+                    Special::SkipTurn => self.players[player].skip_next = true,
+                    Special::ExtraRoll => outcome.extra_turn = true,
+                    Special::Reverse => self.players[player].reverse_next = true,
+                    Special::Teleport => (),
+                }
+                if special == Special::Teleport {
+                    self.free_tile().unwrap_or(landed)
+                } else {
+                    landed
+                }
+            }
+            _ => landed,
+        };
+        self.players[player].position = to;
+        outcome.to = to;
+
+        let safe = matches!(self.board.get(to), Tile::Safe);
+        let mut hit = (0..self.players.len()).filter(|&i| {
+            let p = &self.players[i];
+            !safe
+                && i != player
+                && !self.teammates(player, i)
+                && p.on_board
+                && !self.is_out(i)
+                && p.position == to
+        });
+        match self.rules.mode {
+            Mode::Bump => {
+                for i in hit.collect::<Vec<_>>() {
+                    let p = &mut self.players[i];
+                    p.position = Position { x: 0, y: 0 };
+                    p.on_board = self.rules.entry_roll.is_none();
+                    outcome.events.push(Event::Bumped(i));
+                }
+            }
+            Mode::Swap => {
+                if let Some(i) = hit.next() {
+                    self.players[i].position = from;
+                    outcome.events.push(Event::Swapped(i));
+                }
+            }
+            Mode::Friendly => (),
+        }
+    }
+
+    /// Gives `player` the next free place, and the last place too once a
+    /// single player is left on the board.
+    fn finish(&mut self, player: usize) {
+        let place = self.finish_order().len() + 1;
+        self.players[player].place = Some(place);
+        if let Some(win) = self.rules.teams {
+            self.finish_team(self.players[player].team, win);
+            return;
+        }
+        let remaining: Vec<usize> = (0..self.players.len())
+            .filter(|&i| !self.players[i].finished())
+            .collect();
+        if let [last] = remaining[..] {
+            self.players[last].place = Some(place + 1);
+        }
+        if remaining.len() <= 1 {
+            self.ended = true;
+        }
+    }
+
+    /// Places `team` once it is done, and the last team too once a single
+    /// team is left on the board.
+    fn finish_team(&mut self, team: usize, win: TeamWin) {
+        let done = match win {
+            TeamWin::Any => true,
+            TeamWin::All => self.team_members(team).all(|i| self.players[i].finished()),
+        };
+        if !done || self.team_place(team).is_some() {
+            return;
+        }
+        self.team_places.push(team);
+        let remaining: Vec<usize> = self
+            .teams()
+            .into_iter()
+            .filter(|&t| self.team_place(t).is_none())
+            .collect();
+        if let [last] = remaining[..] {
+            self.team_places.push(last);
+        }
+        if remaining.len() <= 1 {
+            self.ended = true;
+        }
+    }
+
+    /// Opponent furthest ahead of `player`, if anyone is ahead at all.
+    pub fn leader(&self, player: usize) -> Option<usize> {
+        let own = self.board.index(self.players[player].position);
+        (0..self.players.len())
+            .filter(|&i| {
+                let p = &self.players[i];
+                i != player && !self.teammates(player, i) && p.on_board && !self.is_out(i)
+            })
+            .map(|i| (self.board.index(self.players[i].position), i))
+            .filter(|&(index, _)| index > own)
+            .max()
+            .map(|(_, i)| i)
+    }
+
+    /// Whether `player` may play `card` right now. [`Card::ReRoll`] is played
+    /// after the player rolled and before anyone rolls or plays a card, the
+    /// other cards before their roll.
+    pub fn can_play(&self, player: usize, card: Card) -> bool {
+        let p = &self.players[player];
+        if self.ended || self.is_out(player) || !p.cards.contains(&card) {
+            return false;
+        }
+        match card {
+            Card::ReRoll => self.before_roll.as_ref().is_some_and(|before| {
+                before.player_turn == player && before.players[player].cards.contains(&card)
+            }),
+            _ if player != self.player_turn => false,
+            Card::SnakeShield => !p.shield,
+            Card::PlusTwo => p.on_board,
+            Card::SwapWithLeader => p.on_board && self.leader(player).is_some(),
+        }
+    }
+
+    /// Plays `card` from the hand of `player`, returns `None` when
+    /// [`can_play`](Self::can_play) does not allow it.
+    pub fn play_card(&mut self, player: usize, card: Card) -> Option<TurnOutcome> {
+        if !self.can_play(player, card) {
+            return None;
+        }
+        // a re-roll only takes back the roll right before it
+        let before = self.before_roll.take();
+        if card == Card::ReRoll {
+            *self = *before?;
+        }
+        let p = &mut self.players[player];
+        let i = p.cards.iter().position(|&c| c == card)?;
+        p.cards.remove(i);
+        let from = p.position;
+        let mut events = vec![Event::CardPlayed(card)];
+        match card {
+            Card::SnakeShield => p.shield = true,
+            Card::PlusTwo => p.bonus += 2,
+            Card::ReRoll => (),
+            Card::SwapWithLeader => {
+                let leader = self.leader(player)?;
+                self.players[player].position = self.players[leader].position;
+                self.players[leader].position = from;
+                events.push(Event::Swapped(leader));
+            }
+        }
+        Some(TurnOutcome {
+            player,
+            dice: Vec::new(),
+            roll: 0,
+            from,
+            to: self.players[player].position,
+            events,
+            extra_turn: false,
+        })
+    }
+
+    /// Random plain tile nobody stands on, other than the first and the last.
+    fn free_tile(&mut self) -> Option<Position> {
+        let free: Vec<Position> = (1..self.board.last())
+            .map(|i| self.board.position(i))
+            .filter(|&pos| {
+                matches!(self.board.get(pos), Tile::None)
+                    && !(0..self.players.len()).any(|i| {
+                        let p = &self.players[i];
+                        p.on_board && !self.is_out(i) && p.position == pos
+                    })
+            })
+            .collect();
+        free.choose(&mut self.rng).copied()
+    }
+
+    /// Passes the turn to the next player still on the board, passing over
+    /// the ones that have to skip a turn.
+    fn next_turn(&mut self, outcome: &mut TurnOutcome) {
+        if self.ended {
+            return;
+        }
+        let total = self.players.len();
+        // twice around, in case everyone left has to skip
+        for step in 1..=2 * total {
+            let next = (self.player_turn + step) % total;
+            if self.is_out(next) {
+                continue;
+            }
+            if self.players[next].skip_next {
+                self.players[next].skip_next = false;
+                self.players[next].turns += 1;
+                outcome.events.push(Event::Skipped(next));
+                continue;
+            }
+            self.player_turn = next;
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dice::ScriptedDice;
+
+    /// A game on an empty 5x5 board, tiles are zero based indices 0 to 24.
+    fn game(players: usize, rules: Rules) -> GameState {
+        let board = Board::empty(BoardSize {
+            width: 5,
+            height: 5,
+        });
+        let players = (0..players).map(|i| Player::new(format!("P{i}"))).collect();
+        GameState::new(board, players, rules, GameRng::seed_from_u64(0))
+    }
+
+    fn rules(overshoot: OvershootRule) -> Rules {
+        Rules {
+            overshoot,
+            extra_turn: ExtraTurnRule::Off,
+            ..Rules::default()
+        }
+    }
+
+    fn place(state: &mut GameState, player: usize, index: i32) {
+        state.players[player].position = state.board.position(index);
+    }
+
+    fn set(state: &mut GameState, index: i32, tile: Tile) {
+        let pos = state.board.position(index);
+        state.board.tile[pos.y as usize][pos.x as usize] = tile;
+    }
+
+    fn index(state: &GameState, player: usize) -> i32 {
+        state.board.index(state.players[player].position)
+    }
+
+    fn play(state: &mut GameState, rolls: &[u8]) -> Vec<TurnOutcome> {
+        let mut dice = ScriptedDice::new(rolls.iter().copied());
+        std::iter::from_fn(|| state.play_turn(&mut dice)).collect()
+    }
+
+    #[test]
+    fn ladder_on_the_first_tile_is_only_taken_with_a_head_start() {
+        let mut board = Board::empty(BoardSize {
+            width: 5,
+            height: 5,
+        });
+        board.tile[0][0] = Tile::Ladder(board.position(12));
+        let mut players = vec![Player::new("A"), Player::new("B")];
+        players[1].handicap.start = 2;
+        board.tile[0][2] = Tile::Ladder(board.position(8));
+        let state = GameState::new(board, players, Rules::default(), GameRng::seed_from_u64(0));
+        assert_eq!(index(&state, 0), 0);
+        assert_eq!(index(&state, 1), 8);
+    }
+
+    #[test]
+    fn exact_needs_the_exact_roll() {
+        let mut state = game(2, rules(OvershootRule::Exact));
+        place(&mut state, 0, 22);
+        let turns = play(&mut state, &[3, 1, 2]);
+        assert_eq!(turns[0].events, [Event::Overshoot]);
+        assert_eq!(turns[0].to, turns[0].from);
+        assert_eq!(turns[2].events, [Event::Finished(1)]);
+        assert_eq!(index(&state, 0), 24);
+    }
+
+    #[test]
+    fn exact_with_summed_dice_finishes_on_a_single_die() {
+        let mut state = game(2, rules(OvershootRule::Exact));
+        state.rules.dice.count = 2;
+        place(&mut state, 0, 22);
+        let turns = play(&mut state, &[3, 4, 1, 1, 5, 2]);
+        assert_eq!(turns[0].events, [Event::Overshoot]);
+        assert_eq!(turns[2].events, [Event::Finished(1)]);
+    }
+
+    #[test]
+    fn bounce_walks_back_and_takes_the_snake() {
+        let mut state = game(2, rules(OvershootRule::Bounce));
+        let (from, to) = (state.board.position(21), state.board.position(3));
+        state.board.tile[from.y as usize][from.x as usize] = Tile::Snake(to);
+        place(&mut state, 0, 22);
+        let turns = play(&mut state, &[4, 1, 5]);
+        assert_eq!(turns[0].events, [Event::Bounced]);
+        assert_eq!(state.board.index(turns[0].to), 22);
+        assert_eq!(turns[2].events, [Event::Bounced, Event::Snake { from, to }]);
+        assert_eq!(index(&state, 0), 3);
+    }
+
+    #[test]
+    fn pass_finishes_on_any_roll_past_the_end() {
+        let mut state = game(2, rules(OvershootRule::Pass));
+        place(&mut state, 0, 22);
+        let turns = play(&mut state, &[6]);
+        assert_eq!(turns[0].events, [Event::Finished(1)]);
+        assert_eq!(index(&state, 0), 24);
+    }
+
+    #[test]
+    fn bumped_player_needs_the_entry_roll_again() {
+        let mut state = game(
+            2,
+            Rules {
+                mode: Mode::Bump,
+                entry_roll: Some(6),
+                ..rules(OvershootRule::Exact)
+            },
+        );
+        let turns = play(&mut state, &[6, 6, 3, 3, 3]);
+        assert_eq!(turns[0].events, [Event::Entered]);
+        assert_eq!(turns[1].events, [Event::Entered]);
+        assert_eq!(turns[3].events, [Event::Bumped(0)]);
+        assert!(!state.players[0].on_board);
+        assert_eq!(turns[4].events, [Event::NotEntered]);
+        assert_eq!(index(&state, 1), 3);
+    }
+
+    fn three_sixes(penalty: ThreeSixesRule) -> GameState {
+        let mut state = game(
+            2,
+            Rules {
+                extra_turn: ExtraTurnRule::Six,
+                three_sixes: penalty,
+                ..rules(OvershootRule::Exact)
+            },
+        );
+        place(&mut state, 0, 2);
+        let turns = play(&mut state, &[6, 6, 6]);
+        assert!(turns[0].extra_turn && turns[1].extra_turn);
+        assert_eq!(turns[2].events, [Event::ThreeSixes]);
+        assert!(!turns[2].extra_turn);
+        assert_eq!(state.player_turn, 1);
+        state
+    }
+
+    #[test]
+    fn three_sixes_forfeit_keeps_the_first_two_moves() {
+        let state = three_sixes(ThreeSixesRule::Forfeit);
+        assert_eq!(index(&state, 0), 14);
+    }
+
+    #[test]
+    fn three_sixes_back_to_start_undoes_the_turn() {
+        let state = three_sixes(ThreeSixesRule::BackToStart);
+        assert_eq!(index(&state, 0), 2);
+    }
+
+    #[test]
+    fn finish_order_places_the_last_player() {
+        let mut state = game(3, rules(OvershootRule::Pass));
+        place(&mut state, 1, 23);
+        place(&mut state, 2, 20);
+        let turns = play(&mut state, &[1, 1, 1, 1, 3, 1]);
+        assert_eq!(turns.len(), 5);
+        assert_eq!(turns[1].events, [Event::Finished(1)]);
+        assert_eq!(turns[4].events, [Event::Finished(2)]);
+        assert!(state.ended);
+        assert_eq!(state.finish_order(), [1, 2, 0]);
+    }
+
+    fn with_cards() -> GameState {
+        game(
+            2,
+            Rules {
+                cards: true,
+                ..rules(OvershootRule::Exact)
+            },
+        )
+    }
+
+    #[test]
+    fn highest_face_earns_a_card_up_to_the_limit() {
+        let mut state = with_cards();
+        let turns = play(&mut state, &[6, 1]);
+        let card = state.players[0].cards[0];
+        assert_eq!(turns[0].events, [Event::CardEarned(card)]);
+        assert!(turns[1].events.is_empty());
+
+        state.players[0].cards = vec![Card::PlusTwo; MAX_CARDS];
+        let turns = play(&mut state, &[6]);
+        assert!(turns[0].events.is_empty());
+        assert_eq!(state.players[0].cards.len(), MAX_CARDS);
+    }
+
+    #[test]
+    fn snake_shield_ignores_one_snake() {
+        let mut state = with_cards();
+        let (from, to) = (state.board.position(3), state.board.position(1));
+        state.board.tile[from.y as usize][from.x as usize] = Tile::Snake(to);
+        place(&mut state, 0, 1);
+        state.players[0].cards = vec![Card::SnakeShield];
+        assert!(state.play_card(0, Card::SnakeShield).is_some());
+        assert!(state.players[0].cards.is_empty());
+        let turns = play(&mut state, &[2, 1]);
+        assert_eq!(turns[0].events, [Event::Shielded]);
+        assert_eq!(index(&state, 0), 3);
+        place(&mut state, 0, 1);
+        let turns = play(&mut state, &[2]);
+        assert_eq!(turns[0].events, [Event::Snake { from, to }]);
+    }
+
+    #[test]
+    fn plus_two_adds_to_the_next_move_only() {
+        let mut state = with_cards();
+        state.players[0].cards = vec![Card::PlusTwo];
+        state.play_card(0, Card::PlusTwo);
+        play(&mut state, &[1, 1, 1]);
+        assert_eq!(index(&state, 0), 4);
+    }
+
+    #[test]
+    fn swap_with_leader_trades_places() {
+        let mut state = with_cards();
+        place(&mut state, 1, 9);
+        state.players[0].cards = vec![Card::SwapWithLeader];
+        let outcome = state.play_card(0, Card::SwapWithLeader).unwrap();
+        assert_eq!(outcome.events[1], Event::Swapped(1));
+        assert_eq!((index(&state, 0), index(&state, 1)), (9, 0));
+        assert!(!state.can_play(0, Card::SwapWithLeader));
+    }
+
+    #[test]
+    fn re_roll_takes_back_the_last_roll() {
+        let mut state = with_cards();
+        state.players[0].cards = vec![Card::ReRoll];
+        play(&mut state, &[2]);
+        assert_eq!(state.player_turn, 1);
+        assert!(state.play_card(0, Card::ReRoll).is_some());
+        assert_eq!(state.player_turn, 0);
+        assert_eq!(index(&state, 0), 0);
+        assert!(state.players[0].cards.is_empty());
+    }
+
+    #[test]
+    fn re_roll_is_gone_once_another_card_is_played() {
+        let mut state = with_cards();
+        state.players[0].cards = vec![Card::ReRoll];
+        state.players[1].cards = vec![Card::SnakeShield];
+        play(&mut state, &[2]);
+        assert!(state.play_card(1, Card::SnakeShield).is_some());
+        assert!(!state.can_play(0, Card::ReRoll));
+        assert!(state.play_card(0, Card::ReRoll).is_none());
+        assert!(state.players[1].shield);
+        assert_eq!(state.player_turn, 1);
+    }
+
+    #[test]
+    fn re_roll_is_gone_once_someone_else_rolls() {
+        let mut state = with_cards();
+        state.players[0].cards = vec![Card::ReRoll];
+        play(&mut state, &[2, 1]);
+        assert!(!state.can_play(0, Card::ReRoll));
+    }
+
+    fn teams(players: usize, teams: usize, mode: Mode, win: TeamWin) -> GameState {
+        let mut state = game(
+            players,
+            Rules {
+                mode,
+                teams: Some(win),
+                ..rules(OvershootRule::Pass)
+            },
+        );
+        for (i, p) in state.players.iter_mut().enumerate() {
+            p.team = i % teams;
+        }
+        state
+    }
+
+    #[test]
+    fn any_member_finishing_places_the_team() {
+        let mut state = teams(4, 3, Mode::Bump, TeamWin::Any);
+        place(&mut state, 0, 23);
+        place(&mut state, 1, 5);
+        place(&mut state, 3, 10);
+        let turns = play(&mut state, &[1]);
+        assert_eq!(
+            turns[0].events,
+            [
+                Event::Finished(1),
+                Event::TeamFinished { team: 0, place: 1 }
+            ]
+        );
+        // the teammate left behind is out of the game too
+        assert!(state.is_out(3));
+        assert_eq!(state.leader(1), None);
+        let turns = play(&mut state, &[5, 1]);
+        assert!(turns[0].events.is_empty());
+        assert_eq!(index(&state, 3), 10);
+        assert_eq!(state.player_turn, 1);
+    }
+
+    #[test]
+    fn all_members_finishing_places_the_team() {
+        let mut state = teams(4, 2, Mode::Friendly, TeamWin::All);
+        place(&mut state, 0, 23);
+        place(&mut state, 2, 23);
+        let turns = play(&mut state, &[1, 1, 1, 1]);
+        assert_eq!(turns.len(), 3);
+        assert_eq!(turns[0].events, [Event::Finished(1)]);
+        assert_eq!(
+            turns[2].events,
+            [
+                Event::Finished(2),
+                Event::TeamFinished { team: 0, place: 1 }
+            ]
+        );
+        assert_eq!(state.team_place(1), Some(2));
+        assert!(state.ended);
+    }
+
+    #[test]
+    fn teammates_are_not_bumped_or_swapped() {
+        for mode in [Mode::Bump, Mode::Swap] {
+            let mut state = teams(3, 2, mode, TeamWin::Any);
+            place(&mut state, 2, 3);
+            let turns = play(&mut state, &[3]);
+            assert!(turns[0].events.is_empty(), "{mode}");
+            assert_eq!((index(&state, 0), index(&state, 2)), (3, 3));
+        }
+    }
+
+    #[test]
+    fn easy_boards_are_short_on_small_boards() {
+        let size = BoardSize {
+            width: 5,
+            height: 5,
+        };
+        let throws = analysis::Throws::new(&Rules::default()).single_rolls();
+        let plain = analysis::analyze_with(&Board::empty(size), &throws).expected_rolls;
+        let short = (0..10)
+            .filter(|&seed| {
+                let mut rng = GameRng::seed_from_u64(seed);
+                let board = Board::new(size, 0, Difficulty::Easy, &throws, &mut rng);
+                let length = analysis::analyze_with(&board, &throws).expected_rolls / plain;
+                Difficulty::Easy.length_band().contains(&length)
+            })
+            .count();
+        assert!(short >= 7, "only {short} of 10 boards are short enough");
+    }
+
+    /// Every snake and ladder on `board`, as zero based start and end.
+    fn links(board: &Board) -> Vec<(i32, i32, Tile)> {
+        (0..board.tiles() as i32)
+            .filter_map(|i| match board.get(board.position(i)) {
+                tile @ (Tile::Snake(to) | Tile::Ladder(to)) => Some((i, board.index(to), tile)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn generating_a_crowded_board_gives_up() {
+        let spec = BoardSpec {
+            snakes: 60,
+            ..BoardSpec::new(BoardSize {
+                width: 10,
+                height: 10,
+            })
+        };
+        let started = std::time::Instant::now();
+        let err = Board::generate(&spec, &mut GameRng::seed_from_u64(0)).unwrap_err();
+        assert!(matches!(err, GenerationError::TooManySnakes { placed } if placed < 60));
+        assert!(started.elapsed() < std::time::Duration::from_secs(10));
+        let spec = BoardSpec {
+            snakes: 1,
+            ladders: 60,
+            ..spec
+        };
+        let err = Board::generate(&spec, &mut GameRng::seed_from_u64(0)).unwrap_err();
+        assert!(matches!(err, GenerationError::TooManyLadders { placed } if placed < 60));
+    }
+
+    #[test]
+    fn generating_rejects_bad_specs() {
+        let size = BoardSize {
+            width: 5,
+            height: 5,
+        };
+        let mut rng = GameRng::seed_from_u64(0);
+        for (width, height) in [(1, 5), (5, 1), (0, 0)] {
+            let spec = BoardSpec::new(BoardSize { width, height });
+            assert_eq!(
+                Board::generate(&spec, &mut rng).unwrap_err(),
+                GenerationError::TooSmall
+            );
+        }
+        for (min_length, max_length) in [(0, 5), (6, 5)] {
+            let spec = BoardSpec {
+                min_length,
+                max_length,
+                ..BoardSpec::new(size)
+            };
+            assert_eq!(
+                Board::generate(&spec, &mut rng).unwrap_err(),
+                GenerationError::BadLengths
+            );
+        }
+    }
+
+    #[test]
+    fn generated_boards_follow_the_spec() {
+        let spec = BoardSpec {
+            snakes: 6,
+            ladders: 6,
+            min_length: 4,
+            max_length: 30,
+            banned: vec![0, 1, 2, 10, 20, 30, 40, 63],
+            ..BoardSpec::new(BoardSize {
+                width: 8,
+                height: 8,
+            })
+        };
+        for seed in 0..20 {
+            let board = Board::generate(&spec, &mut GameRng::seed_from_u64(seed)).unwrap();
+            let links = links(&board);
+            let snakes = links
+                .iter()
+                .filter(|(_, _, tile)| matches!(tile, Tile::Snake(_)));
+            assert_eq!(snakes.count(), spec.snakes, "seed {seed}");
+            assert_eq!(links.len(), spec.snakes + spec.ladders, "seed {seed}");
+            for &(from, to, tile) in &links {
+                assert!(
+                    !spec.banned.contains(&from),
+                    "seed {seed}: {tile:?} from {from}"
+                );
+                assert!(!spec.banned.contains(&to), "seed {seed}: {tile:?} to {to}");
+                let length = (to - from).abs();
+                assert!(
+                    (spec.min_length..=spec.max_length).contains(&length),
+                    "seed {seed}: {tile:?} from {from} is {length} long"
+                );
+                if let Tile::Snake(_) = tile {
+                    let below = board.below(board.position(from)).map(|pos| board.get(pos));
+                    assert!(
+                        !matches!(below, Some(Tile::Ladder(_))),
+                        "seed {seed}: snake on {from} right above a ladder"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn skip_tile_loses_the_next_turn() {
+        let mut state = game(2, rules(OvershootRule::Exact));
+        set(&mut state, 3, Tile::Special(Special::SkipTurn));
+        let turns = play(&mut state, &[3, 1, 2, 4]);
+        assert_eq!(turns[0].events, [Event::Special(Special::SkipTurn)]);
+        assert_eq!(turns[1].events, [Event::Skipped(0)]);
+        assert_eq!(turns[2].player, 1);
+        // only the one turn is lost
+        assert_eq!(turns[3].player, 0);
+        assert_eq!(index(&state, 0), 7);
+    }
+
+    #[test]
+    fn extra_roll_tile_rolls_again() {
+        let mut state = game(2, rules(OvershootRule::Exact));
+        set(&mut state, 2, Tile::Special(Special::ExtraRoll));
+        let turns = play(&mut state, &[2, 3, 1]);
+        assert!(turns[0].extra_turn);
+        assert_eq!((turns[1].player, index(&state, 0)), (0, 5));
+        assert!(!turns[1].extra_turn);
+        assert_eq!(turns[2].player, 1);
+    }
+
+    #[test]
+    fn reverse_tile_only_turns_the_next_move() {
+        let mut state = game(2, rules(OvershootRule::Exact));
+        set(&mut state, 2, Tile::Special(Special::Reverse));
+        let turns = play(&mut state, &[2, 4, 1]);
+        assert_eq!(turns[0].events, [Event::Special(Special::Reverse)]);
+        assert_eq!(turns[2].events, [Event::Reversed]);
+        assert_eq!(index(&state, 0), 1);
+        let turns = play(&mut state, &[4, 3]);
+        assert!(turns[1].events.is_empty());
+        assert_eq!(index(&state, 0), 4);
+    }
+
+    #[test]
+    fn teleport_lands_on_a_free_plain_tile() {
+        for seed in 0..10 {
+            let mut state = game(2, rules(OvershootRule::Exact));
+            state.rng = GameRng::seed_from_u64(seed);
+            // tile 10 is taken by the other player, 12 is the only one left
+            for i in 1..24 {
+                set(&mut state, i, Tile::Safe);
+            }
+            set(&mut state, 3, Tile::Special(Special::Teleport));
+            set(&mut state, 10, Tile::None);
+            set(&mut state, 12, Tile::None);
+            place(&mut state, 1, 10);
+            let turns = play(&mut state, &[3]);
+            assert_eq!(turns[0].events, [Event::Special(Special::Teleport)]);
+            assert_eq!(index(&state, 0), 12, "seed {seed}");
+            assert_eq!(turns[0].to, state.board.position(12));
+        }
+    }
+}
@@ -0,0 +1,6 @@
+pub mod analysis;
+pub mod board_code;
+pub mod board_file;
+pub mod dice;
+pub mod engine;
+pub mod presets;
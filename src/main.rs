@@ -1,10 +1,10 @@
-use std::ops::Range;
+use std::time::Duration;
 
 use iced::{
     Alignment::Center,
     Background, Border, Color, Element,
     Length::{self, Fill},
-    Pixels, Point, Renderer, Size, Task,
+    Pixels, Point, Renderer, Size, Subscription, Task,
     border::Radius,
     mouse,
     widget::{
@@ -16,22 +16,38 @@ use iced::{
     },
     window::{Position as WindowPosition, Settings},
 };
-use rand::Rng;
+use serde::{Deserialize, Serialize};
+use snakes_and_ladders::{
+    analysis::{self, Analysis, Throws},
+    board_code, board_file,
+    dice::{Combine, DICE_COUNTS, DICE_SIDES, DiceKind, DiceSource, RandomDice},
+    engine::{
+        self, BOARD_DIMENSIONS, Board, BoardSize, Card, Difficulty, Event, ExtraTurnRule, GameRng,
+        GameState, Handicap, Mode, OvershootRule, Position, Rules, Special, TeamWin,
+        ThreeSixesRule, Tile, TurnOutcome,
+    },
+    presets::{PRESETS, Preset},
+};
 
 #[derive(Debug, Clone)]
 enum Message {
     Menu(MenuMsg),
     Game(GameMsg),
+    Replay(ReplayMsg),
+    /// Odds of the board with the given board code, worked out in the
+    /// background.
+    Analyzed(String, Analysis),
 }
 
 enum Screen {
-    Menu(MenuPage),
-    Game(GamePage),
+    Menu(Box<MenuPage>),
+    Game(Box<GamePage>),
+    Replay(Box<ReplayPage>),
 }
 
 impl Default for Screen {
     fn default() -> Self {
-        Screen::Menu(MenuPage::default())
+        Screen::Menu(Box::default())
     }
 }
 
@@ -39,39 +55,122 @@ impl Default for Screen {
 enum MenuMsg {
     LaunchGame(Config),
     PickList(Mode),
+    PickOvershoot(OvershootRule),
+    PickExtraTurn(ExtraTurnRule),
+    ToggleEntryRoll(bool),
+    ToggleTurnLimit(bool),
+    PickTurnLimit(u32),
+    PickEntryRoll(u8),
+    PickThreeSixes(ThreeSixesRule),
+    ToggleTeams(bool),
+    ToggleCards(bool),
+    PickTeamWin(TeamWin),
+    PickTeam(usize, usize),
+    PickStart(usize, i32),
+    PickDiceBonus(usize, u8),
+    PickImmunities(usize, u32),
+    PickDiceSource(DiceKind),
+    PickDiceCount(u8),
+    PickDiceSides(u8),
+    PickCombine(Combine),
+    PickWidth(usize),
+    PickHeight(usize),
+    PickSpecials(u8),
+    PickDifficulty(Difficulty),
+    PickPreset(&'static Preset),
     AddPlayer,
     UpdatePlayer(usize, String),
     RemovePlayer(usize),
+    UpdateSeed(String),
+    RandomSeed,
+    UpdateBoardPath(String),
+    LoadBoard,
+    ClearBoard,
+    UpdateCode(String),
+    /// Recreates the board and rules of a pasted board code.
+    UseCode,
+    /// Picks up the game from the save file.
+    Resume,
+    UpdateReplayPath(String),
+    /// Opens the replay file in the replay viewer.
+    WatchReplay,
 }
 
 #[derive(Debug, Clone, Copy)]
 enum GameMsg {
     GoToMenu,
     RollDice,
+    EnterRoll(u8),
+    PickDie(usize),
+    /// A player plays one of their cards.
+    PlayCard(usize, Card),
+    Undo,
+    Redo,
+    /// Puts the board code on the clipboard.
+    CopyCode,
+    /// A second of the turn timer went by.
+    Tick,
 }
 
-struct App {
-    current: Screen,
+#[derive(Debug, Clone, Copy)]
+enum ReplayMsg {
+    GoToMenu,
+    First,
+    Back,
+    Forward,
+    Last,
+    /// Shows the game after the given number of moves.
+    Jump(u32),
+    TogglePlay,
+    /// A second of autoplay went by.
+    Tick,
+    /// Clicks on the board and side panels, which are only looked at here.
+    Board,
 }
 
-impl Default for App {
-    fn default() -> Self {
-        App {
-            current: Screen::default(),
-        }
-    }
+#[derive(Default)]
+struct App {
+    current: Screen,
 }
 
 impl App {
     fn update(&mut self, message: Message) -> Task<Message> {
         match (&mut self.current, message) {
-            (_, Message::Menu(MenuMsg::LaunchGame(config))) => {
-                self.current = Screen::Game(GamePage::new(config));
-                iced::window::get_latest().and_then(|id| iced::window::maximize(id, true))
+            (Screen::Menu(page), Message::Menu(MenuMsg::LaunchGame(mut config))) => {
+                if config.board.is_some() {
+                    return self.open(Screen::Game(Box::new(GamePage::new(config))));
+                }
+                // generating can take a while, the game starts once it is done
+                page.generating = true;
+                Task::perform(
+                    blocking(move || {
+                        config.board = Some(config.generate_board());
+                        config
+                    }),
+                    |config| Message::Menu(MenuMsg::LaunchGame(config)),
+                )
+            }
+
+            (Screen::Menu(page), Message::Menu(MenuMsg::Resume)) => match GamePage::resume() {
+                Ok(game) => self.open(Screen::Game(Box::new(game))),
+                Err(err) => {
+                    page.load_error = Some(err);
+                    Task::none()
+                }
+            },
+
+            (Screen::Menu(page), Message::Menu(MenuMsg::WatchReplay)) => {
+                match ReplayPage::load(&page.replay_path) {
+                    Ok(replay) => self.open(Screen::Replay(Box::new(replay))),
+                    Err(err) => {
+                        page.load_error = Some(err);
+                        Task::none()
+                    }
+                }
             }
 
-            (_, Message::Game(GameMsg::GoToMenu)) => {
-                self.current = Screen::Menu(MenuPage::default());
+            (_, Message::Game(GameMsg::GoToMenu) | Message::Replay(ReplayMsg::GoToMenu)) => {
+                self.current = Screen::Menu(Box::default());
                 iced::window::get_latest().and_then(|id| iced::window::maximize(id, false))
             }
 
@@ -79,85 +178,196 @@ impl App {
                 page.update(msg);
                 Task::none()
             }
+            (Screen::Game(page), Message::Game(GameMsg::CopyCode)) => {
+                iced::clipboard::write(page.board_code())
+            }
             (Screen::Game(page), Message::Game(msg)) => {
                 page.update(msg);
                 Task::none()
             }
+            (Screen::Replay(page), Message::Replay(msg)) => {
+                page.update(msg);
+                Task::none()
+            }
+            (Screen::Game(page), Message::Analyzed(code, odds)) => {
+                page.set_analysis(&code, odds);
+                Task::none()
+            }
+            (Screen::Replay(replay), Message::Analyzed(code, odds)) => {
+                replay.page.set_analysis(&code, odds);
+                Task::none()
+            }
             _ => Task::none(),
         }
     }
 
+    /// Switches to a game or a replay, maximized, and works out the odds of
+    /// its board in the background.
+    fn open(&mut self, screen: Screen) -> Task<Message> {
+        let analyze = match &screen {
+            Screen::Game(page) => page.analyze(),
+            Screen::Replay(replay) => replay.page.analyze(),
+            Screen::Menu(_) => Task::none(),
+        };
+        self.current = screen;
+        Task::batch([
+            analyze,
+            iced::window::get_latest().and_then(|id| iced::window::maximize(id, true)),
+        ])
+    }
+
+    fn subscription(&self) -> Subscription<Message> {
+        match &self.current {
+            Screen::Game(page) if page.timer_running() => {
+                iced::time::every(Duration::from_secs(1)).map(|_| Message::Game(GameMsg::Tick))
+            }
+            Screen::Replay(page) if page.playing => {
+                iced::time::every(Duration::from_secs(1)).map(|_| Message::Replay(ReplayMsg::Tick))
+            }
+            _ => Subscription::none(),
+        }
+    }
+
     fn view(&self) -> Element<'_, Message> {
         match &self.current {
             Screen::Menu(page) => page.view().map(Message::Menu),
             Screen::Game(page) => page.view().map(Message::Game),
+            Screen::Replay(page) => page.view().map(Message::Replay),
         }
     }
 }
 
-#[derive(Default, Debug, PartialEq, Clone, Copy)]
-pub enum Mode {
-    #[default]
-    Friendly,
-    Bump,
-    Swap,
+#[derive(Default, Debug, Clone)]
+pub struct Config {
+    players: Vec<Player>,
+    possible_players: Vec<Player>,
+    rules: Rules,
+    size: BoardSize,
+    /// Percent of the tiles with a special effect.
+    specials: u8,
+    difficulty: Difficulty,
+    dice_source: DiceKind,
+    seed: u64,
+    /// Seconds a player has for a turn before the game rolls for them.
+    turn_limit: Option<u32>,
+    /// Board loaded from a file or picked, generated from the seed when
+    /// left out.
+    board: Option<Board>,
+    /// Throws of the dice in `rules`, kept from generating the board for the
+    /// odds on the game screen.
+    throws: Option<Throws>,
 }
 
-impl std::fmt::Display for Mode {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_str(match self {
-            Mode::Friendly => "Friendly",
-            Mode::Bump => "Bump",
-            Mode::Swap => "Swap",
-        })
+impl Config {
+    /// The random board for the seed, the same on every machine.
+    fn generate_board(&mut self) -> Board {
+        let mut board_rng = engine::seeded_rngs(self.seed).0;
+        let throws = Throws::new(&self.rules);
+        let board = Board::new(
+            self.size,
+            self.specials,
+            self.difficulty,
+            &throws,
+            &mut board_rng,
+        );
+        self.throws = Some(throws);
+        board
     }
 }
 
-#[derive(Default, Debug, Clone)]
-pub struct Config {
-    players: Vec<Player>,
-    possible_players: Vec<Player>,
-    game_type: Mode,
+/// Runs `work` on the threads tokio keeps for blocking work, so it does not
+/// hold up a runtime worker meanwhile.
+async fn blocking<T: Send + 'static>(work: impl FnOnce() -> T + Send + 'static) -> T {
+    tokio::task::spawn_blocking(work)
+        .await
+        .unwrap_or_else(|err| std::panic::resume_unwind(err.into_panic()))
 }
 
+/// Turn time limits that can be picked, in seconds.
+const TURN_LIMITS: [u32; 6] = [10, 15, 20, 30, 45, 60];
+
+/// Share of special tiles that can be picked, in percent.
+const SPECIAL_PERCENTS: [u8; 5] = [0, 5, 10, 15, 20];
+
+/// Handicaps that can be given to a player: bonus per move and snake
+/// immunities. Start tiles go up to [`BoardSize::max_head_start`].
+const HANDICAP_BONUSES: std::ops::RangeInclusive<u8> = 0..=3;
+const HANDICAP_IMMUNITIES: std::ops::RangeInclusive<u32> = 0..=3;
+
+/// Players that fit on a tile, also the most teams a game can have.
+const MAX_PLAYERS: usize = 4;
+
 struct MenuPage {
     config: Config,
+    seed: String,
+    /// Built-in board in use, if any.
+    preset: Option<&'static Preset>,
+    board_path: String,
+    code: String,
+    /// Why the last board file could not be loaded.
+    board_error: Option<String>,
+    replay_path: String,
+    /// The board of the game to start is being generated.
+    generating: bool,
+    /// Why the saved game or the replay could not be opened.
+    load_error: Option<String>,
 }
 
 impl Default for MenuPage {
     fn default() -> Self {
+        let seed: u64 = rand::random();
         MenuPage {
             config: Config {
                 players: vec![
                     Player {
                         name: String::from("player"),
-                        position: Position { x: 0, y: 0 },
                         color: Color::from_rgb8(255, 0, 0),
                         offset: (25, 25),
+                        team: 0,
+                        handicap: Handicap::default(),
                     },
                     Player {
                         name: String::from("player"),
-                        position: Position { x: 0, y: 0 },
                         color: Color::from_rgb8(0, 255, 0),
                         offset: (25, 75),
+                        team: 1,
+                        handicap: Handicap::default(),
                     },
                 ],
                 possible_players: vec![
                     Player {
                         name: String::from("player"),
-                        position: Position { x: 0, y: 0 },
                         color: Color::from_rgb8(255, 255, 0),
                         offset: (75, 25),
+                        team: 0,
+                        handicap: Handicap::default(),
                     },
                     Player {
                         name: String::from("player"),
-                        position: Position { x: 0, y: 0 },
                         color: Color::from_rgb8(0, 0, 255),
                         offset: (75, 75),
+                        team: 1,
+                        handicap: Handicap::default(),
                     },
                 ],
-                game_type: Mode::default(),
+                rules: Rules::default(),
+                size: BoardSize::default(),
+                specials: 0,
+                difficulty: Difficulty::default(),
+                dice_source: DiceKind::default(),
+                seed,
+                turn_limit: None,
+                board: None,
+                throws: None,
             },
+            seed: seed.to_string(),
+            preset: None,
+            board_path: String::new(),
+            code: String::new(),
+            board_error: None,
+            replay_path: latest_replay().unwrap_or_default(),
+            generating: false,
+            load_error: None,
         }
     }
 }
@@ -165,12 +375,48 @@ impl Default for MenuPage {
 impl MenuPage {
     fn update(&mut self, message: MenuMsg) {
         match message {
-            MenuMsg::PickList(mode) => self.config.game_type = mode,
+            MenuMsg::PickList(mode) => self.config.rules.mode = mode,
+            MenuMsg::PickOvershoot(rule) => self.config.rules.overshoot = rule,
+            MenuMsg::PickExtraTurn(rule) => self.config.rules.extra_turn = rule,
+            // the highest face, a 6 on normal dice
+            MenuMsg::ToggleEntryRoll(on) => {
+                self.config.rules.entry_roll = on.then_some(self.config.rules.dice.sides)
+            }
+            MenuMsg::ToggleTurnLimit(on) => self.config.turn_limit = on.then_some(30),
+            MenuMsg::PickTurnLimit(seconds) => self.config.turn_limit = Some(seconds),
+            MenuMsg::PickEntryRoll(value) => self.config.rules.entry_roll = Some(value),
+            MenuMsg::PickThreeSixes(rule) => self.config.rules.three_sixes = rule,
+            MenuMsg::ToggleTeams(on) => self.config.rules.teams = on.then_some(TeamWin::Any),
+            MenuMsg::ToggleCards(on) => self.config.rules.cards = on,
+            MenuMsg::PickTeamWin(win) => self.config.rules.teams = Some(win),
+            MenuMsg::PickTeam(i, team) => self.config.players[i].team = team,
+            MenuMsg::PickStart(i, tile) => self.config.players[i].handicap.start = tile - 1,
+            MenuMsg::PickDiceBonus(i, bonus) => self.config.players[i].handicap.dice_bonus = bonus,
+            MenuMsg::PickImmunities(i, count) => {
+                self.config.players[i].handicap.snake_immunities = count
+            }
+            MenuMsg::PickDiceSource(source) => self.config.dice_source = source,
+            MenuMsg::PickDiceCount(count) => self.config.rules.dice.count = count,
+            MenuMsg::PickDiceSides(sides) => {
+                self.config.rules.dice.sides = sides;
+                if let Some(entry) = &mut self.config.rules.entry_roll {
+                    *entry = (*entry).min(sides);
+                }
+            }
+            MenuMsg::PickCombine(combine) => self.config.rules.dice.combine = combine,
+            MenuMsg::PickWidth(width) => self.config.size.width = width,
+            MenuMsg::PickHeight(height) => self.config.size.height = height,
+            MenuMsg::PickSpecials(percent) => self.config.specials = percent,
+            MenuMsg::PickDifficulty(difficulty) => self.config.difficulty = difficulty,
+            MenuMsg::PickPreset(preset) => {
+                self.config.board = Some(preset.board());
+                self.preset = Some(preset);
+                self.board_error = None;
+            }
             MenuMsg::UpdatePlayer(i, s) => self.config.players[i].name = s,
             MenuMsg::AddPlayer => {
                 if let Some(p) = self.config.possible_players.pop() {
                     self.config.players.push(p);
-                } else {
                 }
             }
             MenuMsg::RemovePlayer(i) => {
@@ -178,23 +424,163 @@ impl MenuPage {
                     .possible_players
                     .push(self.config.players.remove(i));
             }
+            MenuMsg::UpdateSeed(s) => {
+                if s.is_empty() {
+                    self.seed = s;
+                } else if let Ok(seed) = s.parse() {
+                    self.config.seed = seed;
+                    self.seed = s;
+                }
+            }
+            MenuMsg::RandomSeed => {
+                self.config.seed = rand::random();
+                self.seed = self.config.seed.to_string();
+            }
+            MenuMsg::UpdateBoardPath(path) => self.board_path = path,
+            MenuMsg::LoadBoard => match board_file::load(&self.board_path) {
+                Ok(board) => {
+                    self.preset = None;
+                    self.config.board = Some(board);
+                    self.board_error = None;
+                }
+                Err(err) => {
+                    self.config.board = None;
+                    self.preset = None;
+                    self.board_error = Some(err.to_string());
+                }
+            },
+            MenuMsg::ClearBoard => {
+                self.config.board = None;
+                self.preset = None;
+            }
+            MenuMsg::UpdateCode(code) => self.code = code,
+            MenuMsg::UseCode => match board_code::decode(&self.code) {
+                Ok((board, rules)) => {
+                    self.config.board = Some(board);
+                    self.config.rules = rules;
+                    self.preset = None;
+                    self.board_error = None;
+                }
+                Err(err) => {
+                    self.config.board = None;
+                    self.preset = None;
+                    self.board_error = Some(err.to_string());
+                }
+            },
+            MenuMsg::UpdateReplayPath(path) => self.replay_path = path,
             _ => {}
         }
     }
 
+    /// The board file in use, or why it could not be loaded.
+    fn loaded_board(&self) -> Element<'_, MenuMsg> {
+        match (&self.config.board, &self.board_error) {
+            (Some(board), _) => {
+                let mut label = if board.name.is_empty() {
+                    String::from("Custom board")
+                } else {
+                    board.name.clone()
+                };
+                if !board.author.is_empty() {
+                    label = format!("{label} by {}", board.author);
+                }
+                widget::row![
+                    widget::text(format!("{label} ({})", board.size())).size(20),
+                    widget::button("Clear").on_press(MenuMsg::ClearBoard),
+                ]
+                .spacing(10)
+                .align_y(Center)
+                .into()
+            }
+            (None, Some(err)) => widget::text(err.as_str())
+                .size(16)
+                .color(Color::from_rgb8(255, 85, 85))
+                .into(),
+            (None, None) => widget::text("No preset or board file, a random board is generated")
+                .size(16)
+                .into(),
+        }
+    }
+
     fn view(&self) -> Element<'_, MenuMsg> {
         let pick = PickList::new(
             [Mode::Friendly, Mode::Bump, Mode::Swap],
-            Some(self.config.game_type),
+            Some(self.config.rules.mode),
             MenuMsg::PickList,
         );
+        let pick_overshoot = PickList::new(
+            [
+                OvershootRule::Exact,
+                OvershootRule::Bounce,
+                OvershootRule::Pass,
+            ],
+            Some(self.config.rules.overshoot),
+            MenuMsg::PickOvershoot,
+        );
+        let pick_three_sixes = PickList::new(
+            [
+                ThreeSixesRule::Off,
+                ThreeSixesRule::Forfeit,
+                ThreeSixesRule::BackToStart,
+            ],
+            Some(self.config.rules.three_sixes),
+            MenuMsg::PickThreeSixes,
+        );
+        let pick_dice_source = PickList::new(
+            [DiceKind::Seeded, DiceKind::Random, DiceKind::Manual],
+            Some(self.config.dice_source),
+            MenuMsg::PickDiceSource,
+        );
+        let pick_extra_turn = PickList::new(
+            [
+                ExtraTurnRule::Off,
+                ExtraTurnRule::Six,
+                ExtraTurnRule::Doubles,
+            ],
+            Some(self.config.rules.extra_turn),
+            MenuMsg::PickExtraTurn,
+        );
+        let size = match &self.config.board {
+            Some(board) => board.size(),
+            None => self.config.size,
+        };
+        let max_start = size.max_head_start();
         let players: Vec<Element<MenuMsg>> = self
             .config
             .players
             .iter()
             .enumerate()
             .map(|(i, s)| {
-                if self.config.players.len() > 2 {
+                let team = self.config.rules.teams.map(|_| {
+                    PickList::new(
+                        (1..=MAX_PLAYERS).collect::<Vec<_>>(),
+                        Some(s.team + 1),
+                        move |t| MenuMsg::PickTeam(i, t - 1),
+                    )
+                });
+                let handicap = row![
+                    widget::text("Start"),
+                    PickList::new(
+                        (1..=max_start + 1).collect::<Vec<_>>(),
+                        Some(s.handicap.start.min(max_start) + 1),
+                        move |tile| MenuMsg::PickStart(i, tile),
+                    ),
+                    widget::text("Bonus +"),
+                    PickList::new(
+                        HANDICAP_BONUSES.collect::<Vec<_>>(),
+                        Some(s.handicap.dice_bonus),
+                        move |bonus| MenuMsg::PickDiceBonus(i, bonus),
+                    ),
+                    widget::text("Snake immunities"),
+                    PickList::new(
+                        HANDICAP_IMMUNITIES.collect::<Vec<_>>(),
+                        Some(s.handicap.snake_immunities),
+                        move |count| MenuMsg::PickImmunities(i, count),
+                    ),
+                ]
+                .spacing(10)
+                .align_y(Center);
+                let details: Element<MenuMsg> = if self.config.players.len() > 2 {
                     row![
                         Element::from(widget::text_input("not allowed", &s.name).on_input(
                             move |v| {
@@ -211,12 +597,13 @@ impl MenuPage {
                             .width(Length::Fixed(20.0))
                             .style(|_| {
                                 Style {
-                                    background: Some(Background::Color(s.color.clone())),
+                                    background: Some(Background::Color(s.color)),
                                     ..Default::default()
                                 }
                             }),
-                        Element::from(widget::button("X").on_press(MenuMsg::RemovePlayer(i)))
                     ]
+                    .push_maybe(team)
+                    .push(widget::button("X").on_press(MenuMsg::RemovePlayer(i)))
                     .align_y(Center)
                     .spacing(10)
                     .into()
@@ -238,141 +625,402 @@ impl MenuPage {
                                 .width(Length::Fixed(20.0))
                                 .style(|_| {
                                     Style {
-                                        background: Some(Background::Color(s.color.clone())),
+                                        background: Some(Background::Color(s.color)),
                                         ..Default::default()
                                     }
                                 }),
                         ]
+                        .push_maybe(team)
                         .align_y(Center)
                         .spacing(10),
                     )
                     .align_x(Center)
                     .align_y(Center)
                     .into()
-                }
+                };
+                column![details, handicap].spacing(5).into()
             })
             .collect();
         let players = widget::column(players).spacing(10).padding(10);
-        widget::column![
-            widget::text("Main Menu").size(30),
-            widget::row![widget::text("Select Game Mode:").size(20), pick,]
+        widget::scrollable(
+            widget::column![
+                widget::text("Main Menu").size(30),
+                widget::row![widget::text("Select Game Mode:").size(20), pick,]
+                    .spacing(10)
+                    .align_y(Center),
+                widget::row![widget::text("Overshoot:").size(20), pick_overshoot,]
+                    .spacing(10)
+                    .align_y(Center),
+                widget::row![widget::text("Extra turn:").size(20), pick_extra_turn,]
+                    .spacing(10)
+                    .align_y(Center),
+                widget::row![widget::text("Three sixes:").size(20), pick_three_sixes,]
+                    .spacing(10)
+                    .align_y(Center),
+                widget::row![
+                    widget::checkbox("Teams", self.config.rules.teams.is_some())
+                        .on_toggle(MenuMsg::ToggleTeams)
+                        .size(20),
+                    PickList::new(
+                        [TeamWin::Any, TeamWin::All],
+                        self.config.rules.teams,
+                        MenuMsg::PickTeamWin,
+                    ),
+                ]
                 .spacing(10)
                 .align_y(Center),
-            widget::row![
-                widget::text("Enter player details:").size(20),
-                widget::button(widget::text("Add").align_x(Center))
-                    .on_press(MenuMsg::AddPlayer)
-                    .width(iced::Length::Fill),
-            ]
-            .spacing(10)
-            .padding(10)
-            .align_y(Center),
-            players,
-            widget::container(
-                widget::button(
-                    widget::text("Start Game")
+                widget::checkbox("Power-up cards", self.config.rules.cards)
+                    .on_toggle(MenuMsg::ToggleCards)
+                    .size(20),
+                widget::row![
+                    widget::checkbox("Roll to enter", self.config.rules.entry_roll.is_some())
+                        .on_toggle(MenuMsg::ToggleEntryRoll)
+                        .size(20),
+                    PickList::new(
+                        (1..=self.config.rules.dice.sides).collect::<Vec<u8>>(),
+                        self.config.rules.entry_roll,
+                        MenuMsg::PickEntryRoll,
+                    ),
+                ]
+                .spacing(10)
+                .align_y(Center),
+                widget::row![
+                    widget::text("Board:").size(20),
+                    PickList::new(
+                        BOARD_DIMENSIONS.collect::<Vec<_>>(),
+                        Some(self.config.size.width),
+                        MenuMsg::PickWidth,
+                    ),
+                    widget::text("x").size(20),
+                    PickList::new(
+                        BOARD_DIMENSIONS.collect::<Vec<_>>(),
+                        Some(self.config.size.height),
+                        MenuMsg::PickHeight,
+                    ),
+                ]
+                .spacing(10)
+                .align_y(Center),
+                widget::row![
+                    widget::text("Special tiles:").size(20),
+                    PickList::new(
+                        SPECIAL_PERCENTS,
+                        Some(self.config.specials),
+                        MenuMsg::PickSpecials,
+                    ),
+                    widget::text("%").size(20),
+                ]
+                .spacing(10)
+                .align_y(Center),
+                widget::row![
+                    widget::text("Difficulty:").size(20),
+                    PickList::new(
+                        Difficulty::ALL,
+                        Some(self.config.difficulty),
+                        MenuMsg::PickDifficulty,
+                    ),
+                ]
+                .spacing(10)
+                .align_y(Center),
+                widget::row![
+                    widget::text("Dice:").size(20),
+                    PickList::new(
+                        DICE_COUNTS.collect::<Vec<_>>(),
+                        Some(self.config.rules.dice.count),
+                        MenuMsg::PickDiceCount,
+                    ),
+                    widget::text("d").size(20),
+                    PickList::new(
+                        DICE_SIDES,
+                        Some(self.config.rules.dice.sides),
+                        MenuMsg::PickDiceSides,
+                    ),
+                    PickList::new(
+                        [Combine::Sum, Combine::Pick],
+                        Some(self.config.rules.dice.combine),
+                        MenuMsg::PickCombine,
+                    ),
+                ]
+                .spacing(10)
+                .align_y(Center),
+                widget::row![widget::text("Rolled by:").size(20), pick_dice_source,]
+                    .spacing(10)
+                    .align_y(Center),
+                widget::row![
+                    widget::checkbox("Turn timer", self.config.turn_limit.is_some())
+                        .on_toggle(MenuMsg::ToggleTurnLimit)
+                        .size(20),
+                    PickList::new(TURN_LIMITS, self.config.turn_limit, MenuMsg::PickTurnLimit,),
+                    widget::text("s").size(20),
+                ]
+                .spacing(10)
+                .align_y(Center),
+                widget::row![
+                    widget::text("Seed:").size(20),
+                    widget::text_input("seed", &self.seed).on_input(MenuMsg::UpdateSeed),
+                    widget::button("New").on_press(MenuMsg::RandomSeed),
+                ]
+                .spacing(10)
+                .padding(10)
+                .align_y(Center),
+                widget::row![
+                    widget::text("Preset:").size(20),
+                    PickList::new(
+                        PRESETS.iter().collect::<Vec<_>>(),
+                        self.preset,
+                        MenuMsg::PickPreset,
+                    ),
+                ]
+                .spacing(10)
+                .align_y(Center),
+                widget::row![
+                    widget::text("Board file:").size(20),
+                    widget::text_input("path/to/board.json", &self.board_path)
+                        .on_input(MenuMsg::UpdateBoardPath)
+                        .on_submit(MenuMsg::LoadBoard),
+                    widget::button("Load board").on_press(MenuMsg::LoadBoard),
+                ]
+                .spacing(10)
+                .padding(10)
+                .align_y(Center),
+                widget::row![
+                    widget::text("Board code:").size(20),
+                    widget::text_input("paste a board code", &self.code)
+                        .on_input(MenuMsg::UpdateCode)
+                        .on_submit(MenuMsg::UseCode),
+                    widget::button("Use code").on_press(MenuMsg::UseCode),
+                ]
+                .spacing(10)
+                .padding(10)
+                .align_y(Center),
+                self.loaded_board(),
+                widget::row![
+                    widget::text("Enter player details:").size(20),
+                    widget::button(widget::text("Add").align_x(Center))
+                        .on_press(MenuMsg::AddPlayer)
+                        .width(iced::Length::Fill),
+                ]
+                .spacing(10)
+                .padding(10)
+                .align_y(Center),
+                players,
+                widget::container(
+                    widget::button(
+                        widget::text(if self.generating {
+                            "Generating board..."
+                        } else {
+                            "Start Game"
+                        })
                         .size(20)
                         .align_x(Center)
                         .align_y(Center)
                         .width(Fill)
+                    )
+                    .padding(10)
+                    .on_press_maybe(
+                        (!self.generating).then(|| MenuMsg::LaunchGame(self.config.clone()))
+                    )
+                    .width(iced::Length::Fill)
                 )
+                .padding(10),
+                widget::container(
+                    widget::button(
+                        widget::text("Resume")
+                            .size(20)
+                            .align_x(Center)
+                            .align_y(Center)
+                            .width(Fill)
+                    )
+                    .padding(10)
+                    .on_press_maybe(
+                        std::path::Path::new(SAVE_FILE)
+                            .exists()
+                            .then_some(MenuMsg::Resume)
+                    )
+                    .width(iced::Length::Fill)
+                )
+                .padding(10),
+            ]
+            .spacing(10)
+            .padding(10)
+            .align_x(Center)
+            .push(
+                widget::row![
+                    widget::text("Replay file:").size(20),
+                    widget::text_input("replays/game.json", &self.replay_path)
+                        .on_input(MenuMsg::UpdateReplayPath)
+                        .on_submit(MenuMsg::WatchReplay),
+                    widget::button("Watch replay").on_press(MenuMsg::WatchReplay),
+                ]
+                .spacing(10)
                 .padding(10)
-                .on_press(MenuMsg::LaunchGame(self.config.clone()))
-                .width(iced::Length::Fill)
+                .align_y(Center),
             )
-            .padding(10),
-        ]
-        .spacing(10)
-        .align_x(Center)
+            .push_maybe(self.load_error.as_ref().map(|err| {
+                widget::text(err.as_str())
+                    .size(16)
+                    .color(Color::from_rgb8(255, 85, 85))
+            })),
+        )
         .into()
     }
 }
 
-#[derive(Debug, Clone, Copy)]
-enum Tile {
-    Snake(Position),
-    Ladder(Position),
-    Target,
-    None,
-}
+/// Side of the square the board is drawn in, tiles shrink to fit it.
+const BOARD_SIZE: f32 = 1000.0;
 
-#[derive(Debug, Clone)]
-struct Board {
-    tile: [[Tile; 10]; 10],
-    players: Vec<Player>,
-}
+impl GamePage {
+    /// Side of a single tile, in pixels.
+    fn tile_size(&self) -> f32 {
+        BOARD_SIZE / self.state.board.width.max(self.state.board.height) as f32
+    }
 
-#[derive(Debug, PartialEq, Clone, Copy)]
-struct Position {
-    x: i32,
-    y: i32,
-}
-
-impl Board {
-    pub const TILE_SIZE: i32 = 100;
-    fn add_snl(
-        num: u8,
-        tile: &mut [[Tile; 10]; 10],
-        r_start: Range<usize>,
-        r_end: fn(usize) -> Range<usize>,
-        make_tile: impl Fn(Position) -> Tile,
-    ) {
-        let mut rng = rand::thread_rng();
-        let max_attempts = 50;
-        let mut attempts = 0;
-        for _ in 0..num {
-            'outer: loop {
-                let pos_start = rng.gen_range(r_start.clone());
-                if !matches!(tile[pos_start / 10][pos_start % 10], Tile::None) {
-                    continue;
-                }
-                loop {
-                    let pos_end = rng.gen_range(r_end(pos_start));
-                    if !matches!(tile[pos_end / 10][pos_end % 10], Tile::None) {
-                        attempts += 1;
-                        if attempts > max_attempts {
-                            continue 'outer;
-                        }
-                        continue;
-                    }
-                    attempts = 0;
-                    tile[pos_start / 10][pos_start % 10] = make_tile(Position {
-                        x: (pos_end % 10) as i32,
-                        y: (pos_end / 10) as i32,
-                    });
-                    tile[pos_end / 10][pos_end % 10] = Tile::Target;
-                    break;
-                }
-                break;
-            }
+    /// Top left corner of the tile at `pos` on the canvas.
+    fn tile_origin(&self, pos: Position) -> Point {
+        let board = &self.state.board;
+        let row = board.height as i32 - 1 - pos.y;
+        let col = if pos.y & 1 == 0 {
+            pos.x
+        } else {
+            board.width as i32 - 1 - pos.x
+        };
+        Point {
+            x: col as f32 * self.tile_size(),
+            y: row as f32 * self.tile_size(),
         }
     }
 
-    fn new(players: Vec<Player>) -> Self {
-        let mut tile: [[Tile; 10]; 10] = [[Tile::None; 10]; 10];
-        // add snakes
-        Board::add_snl(
-            8,
-            &mut tile,
-            10..99,
-            |pos| 1..(pos / 10) * 10,
-            |pos| Tile::Snake(pos),
-        );
-        // add ladders
-        Board::add_snl(
-            8,
-            &mut tile,
-            1..90,
-            |pos| (pos / 10 + 1) * 10..99,
-            |pos| Tile::Ladder(pos),
+    /// Tile under the canvas point `p`, if any.
+    fn tile_at(&self, p: Point) -> Option<Position> {
+        let board = &self.state.board;
+        let col = (p.x / self.tile_size()) as i32;
+        let row = (p.y / self.tile_size()) as i32;
+        if p.x < 0.0 || p.y < 0.0 || col >= board.width as i32 || row >= board.height as i32 {
+            return None;
+        }
+        let y = board.height as i32 - 1 - row;
+        let x = if y & 1 == 0 {
+            col
+        } else {
+            board.width as i32 - 1 - col
+        };
+        Some(Position { x, y })
+    }
+
+    fn tile_center(&self, pos: Position) -> Point {
+        let p = self.tile_origin(pos);
+        Point {
+            x: p.x + self.tile_size() / 2.0,
+            y: p.y + self.tile_size() / 2.0,
+        }
+    }
+
+    /// Icon of a special tile, drawn at the bottom of the tile between the
+    /// player tokens.
+    fn draw_special(frame: &mut canvas::Frame, special: Special, origin: Point, scale: f32) {
+        let center = Point {
+            x: origin.x + 50.0 * scale,
+            y: origin.y + 80.0 * scale,
+        };
+        let r = 11.0 * scale;
+        let (path, color) = match special {
+            // no entry sign
+            Special::SkipTurn => (
+                Path::new(|b| {
+                    b.circle(center, r);
+                    b.move_to(Point::new(center.x - r * 0.7, center.y + r * 0.7));
+                    b.line_to(Point::new(center.x + r * 0.7, center.y - r * 0.7));
+                }),
+                Color::from_rgb8(255, 140, 0),
+            ),
+            // a die with a single pip
+            Special::ExtraRoll => (
+                Path::new(|b| {
+                    b.rounded_rectangle(
+                        Point::new(center.x - r, center.y - r),
+                        Size::new(2.0 * r, 2.0 * r),
+                        Radius::from(3.0 * scale),
+                    );
+                    b.circle(center, 2.0 * scale);
+                }),
+                Color::from_rgb8(0, 120, 255),
+            ),
+            // a portal
+            Special::Teleport => (
+                Path::new(|b| {
+                    b.circle(center, r);
+                    b.circle(center, r * 0.5);
+                }),
+                Color::from_rgb8(150, 0, 200),
+            ),
+            // an arrow pointing back
+            Special::Reverse => (
+                Path::new(|b| {
+                    b.move_to(Point::new(center.x + r, center.y));
+                    b.line_to(Point::new(center.x - r, center.y));
+                    b.move_to(Point::new(center.x - r * 0.4, center.y - r * 0.6));
+                    b.line_to(Point::new(center.x - r, center.y));
+                    b.line_to(Point::new(center.x - r * 0.4, center.y + r * 0.6));
+                }),
+                Color::from_rgb8(140, 70, 0),
+            ),
+        };
+        frame.stroke(
+            &path,
+            Stroke {
+                style: canvas::Style::Solid(color),
+                width: 3.0 * scale,
+                ..Default::default()
+            },
         );
-        // dbg!(&tile);
-        Board { tile, players }
     }
 }
 
-impl<GameMsg> canvas::Program<GameMsg> for Board {
+impl canvas::Program<GameMsg> for GamePage {
     type State = ();
+
+    fn update(
+        &self,
+        _state: &mut Self::State,
+        event: canvas::Event,
+        bounds: iced::Rectangle,
+        cursor: mouse::Cursor,
+    ) -> (canvas::event::Status, Option<GameMsg>) {
+        let Some(choice) = &self.pending_pick else {
+            return (canvas::event::Status::Ignored, None);
+        };
+        let clicked = matches!(
+            event,
+            canvas::Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left))
+        );
+        match cursor
+            .position_in(bounds)
+            .and_then(|p| self.tile_at(p))
+            .and_then(|pos| choice.die_at(pos))
+        {
+            Some(i) if clicked => (canvas::event::Status::Captured, Some(GameMsg::PickDie(i))),
+            _ => (canvas::event::Status::Ignored, None),
+        }
+    }
+
+    fn mouse_interaction(
+        &self,
+        _state: &Self::State,
+        bounds: iced::Rectangle,
+        cursor: mouse::Cursor,
+    ) -> mouse::Interaction {
+        let over_choice = self.pending_pick.as_ref().is_some_and(|choice| {
+            cursor
+                .position_in(bounds)
+                .and_then(|p| self.tile_at(p))
+                .and_then(|pos| choice.die_at(pos))
+                .is_some()
+        });
+        if over_choice {
+            mouse::Interaction::Pointer
+        } else {
+            mouse::Interaction::default()
+        }
+    }
     fn draw(
         &self,
         _state: &Self::State,
@@ -384,41 +1032,41 @@ impl<GameMsg> canvas::Program<GameMsg> for Board {
         let mut frame = canvas::Frame::new(
             renderer,
             Size {
-                width: 1000 as f32,
-                height: 1000 as f32,
+                width: BOARD_SIZE,
+                height: BOARD_SIZE,
             },
         );
 
+        let board = &self.state.board;
+        let tile_size = self.tile_size();
+        // player offsets and sizes are laid out for a 100px tile
+        let scale = tile_size / 100.0;
+
         let mut v: Vec<Path> = vec![Path::rectangle(
             Point::ORIGIN,
             Size {
-                width: 10 as f32 * Board::TILE_SIZE as f32,
-                height: 10 as f32 * Board::TILE_SIZE as f32,
+                width: board.width as f32 * tile_size,
+                height: board.height as f32 * tile_size,
             },
         )];
 
         let mut v_snake: Vec<Path> = Vec::new();
         let mut v_ladder: Vec<Path> = Vec::new();
+        let mut v_safe: Vec<Path> = Vec::new();
+        let mut v_special: Vec<(Special, Point)> = Vec::new();
         let mut v_player: Vec<(Path, Color)> = Vec::new();
+        let mut v_waiting: Vec<(Path, Color)> = Vec::new();
 
-        for tile_index in 0..100 {
-            let tile = self.tile[tile_index / 10][tile_index % 10];
-            let row = 9 - tile_index / 10;
-            let col = tile_index % 10;
-            let col = if row & 1 == 1 { col } else { 9 - col };
+        for tile_index in 0..board.tiles() as i32 {
+            let pos = board.position(tile_index);
+            let tile = board.get(pos);
             let s = format!("{}", tile_index + 1);
-            let p = Point {
-                x: col as f32 * Board::TILE_SIZE as f32,
-                y: row as f32 * Board::TILE_SIZE as f32,
-            };
+            let p = self.tile_origin(pos);
             let text = canvas::Text {
                 content: s,
-                position: Point {
-                    x: p.x + Board::TILE_SIZE as f32 / 2.0,
-                    y: p.y + Board::TILE_SIZE as f32 / 2.0,
-                },
+                position: self.tile_center(pos),
                 color: Color::from_rgba8(200, 0, 255, 0.7),
-                size: Pixels::from(30),
+                size: Pixels::from(30.0 * scale),
                 horizontal_alignment: iced::alignment::Horizontal::Center,
                 vertical_alignment: iced::alignment::Vertical::Center,
                 ..Default::default()
@@ -426,36 +1074,24 @@ impl<GameMsg> canvas::Program<GameMsg> for Board {
             v.push(Path::rectangle(
                 p,
                 Size {
-                    width: Board::TILE_SIZE as f32,
-                    height: Board::TILE_SIZE as f32,
+                    width: tile_size,
+                    height: tile_size,
                 },
             ));
             match tile {
-                Tile::Snake(pos) => {
-                    let r = 9 - pos.y;
-                    let c = if r & 1 == 1 { pos.x } else { 9 - pos.x };
-                    v_snake.push(Path::line(
-                        Point {
-                            x: p.x + Board::TILE_SIZE as f32 / 2.0,
-                            y: p.y + Board::TILE_SIZE as f32 / 2.0,
-                        },
-                        Point {
-                            x: c as f32 * Board::TILE_SIZE as f32 + Board::TILE_SIZE as f32 / 2.0,
-                            y: r as f32 * Board::TILE_SIZE as f32 + Board::TILE_SIZE as f32 / 2.0,
-                        },
-                    ));
+                Tile::Snake(to) => {
+                    v_snake.push(Path::line(self.tile_center(pos), self.tile_center(to)));
                 }
-                Tile::Ladder(pos) => {
-                    let r = 9 - pos.y;
-                    let c = if r & 1 == 1 { pos.x } else { 9 - pos.x };
-                    v_ladder.push(Path::line(
-                        Point {
-                            x: p.x + Board::TILE_SIZE as f32 / 2.0,
-                            y: p.y + Board::TILE_SIZE as f32 / 2.0,
-                        },
-                        Point {
-                            x: c as f32 * Board::TILE_SIZE as f32 + Board::TILE_SIZE as f32 / 2.0,
-                            y: r as f32 * Board::TILE_SIZE as f32 + Board::TILE_SIZE as f32 / 2.0,
+                Tile::Ladder(to) => {
+                    v_ladder.push(Path::line(self.tile_center(pos), self.tile_center(to)));
+                }
+                Tile::Special(special) => v_special.push((special, p)),
+                Tile::Safe => {
+                    v_safe.push(Path::rectangle(
+                        p,
+                        Size {
+                            width: tile_size,
+                            height: tile_size,
                         },
                     ));
                 }
@@ -465,23 +1101,29 @@ impl<GameMsg> canvas::Program<GameMsg> for Board {
         }
 
         frame.fill(&v[0], Color::WHITE);
+        for p in v_safe {
+            frame.fill(&p, Color::from_rgb8(180, 220, 255));
+        }
 
         for p in v {
             frame.stroke(
                 &p,
                 Stroke {
                     style: canvas::Style::Solid(Color::BLACK),
-                    width: 3 as f32,
+                    width: 3.0,
                     ..Default::default()
                 },
             );
         }
+        for (special, origin) in v_special {
+            Self::draw_special(&mut frame, special, origin, scale);
+        }
         for p in v_snake {
             frame.stroke(
                 &p,
                 Stroke {
                     style: canvas::Style::Solid(Color::from_rgb8(255, 0, 0)),
-                    width: 3 as f32,
+                    width: 3.0,
                     ..Default::default()
                 },
             );
@@ -491,128 +1133,562 @@ impl<GameMsg> canvas::Program<GameMsg> for Board {
                 &p,
                 Stroke {
                     style: canvas::Style::Solid(Color::from_rgb8(0, 255, 0)),
-                    width: 3 as f32,
+                    width: 3.0,
                     ..Default::default()
                 },
             );
         }
 
-        for p in self.players.iter() {
-            let pos = p.position;
-            let r = 9 - pos.y;
-            let c = if r & 1 == 1 { pos.x } else { 9 - pos.x };
-            v_player.push((
+        // tiles the current player can pick between
+        if let Some(choice) = &self.pending_pick {
+            let color = self.players[self.state.player_turn].color;
+            for (face, preview) in choice.faces.iter().zip(choice.previews.iter()) {
+                let landed = PendingChoice::landed(preview);
+                for pos in [landed, preview.to] {
+                    let tile = Path::rectangle(
+                        self.tile_origin(pos),
+                        Size {
+                            width: tile_size,
+                            height: tile_size,
+                        },
+                    );
+                    frame.fill(&tile, Color { a: 0.3, ..color });
+                    frame.stroke(
+                        &tile,
+                        Stroke {
+                            style: canvas::Style::Solid(color),
+                            width: 6.0 * scale,
+                            ..Default::default()
+                        },
+                    );
+                }
+                let origin = self.tile_origin(landed);
+                frame.fill_text(canvas::Text {
+                    content: face.to_string(),
+                    position: Point {
+                        x: origin.x + 8.0 * scale,
+                        y: origin.y + 4.0 * scale,
+                    },
+                    color: Color::BLACK,
+                    size: Pixels::from(24.0 * scale),
+                    ..Default::default()
+                });
+            }
+        }
+
+        for (p, state) in self.players.iter().zip(self.state.players.iter()) {
+            let origin = self.tile_origin(state.position);
+            let token = (
                 Path::circle(
                     Point {
-                        x: c as f32 * Board::TILE_SIZE as f32 + p.offset.0 as f32,
-                        y: r as f32 * Board::TILE_SIZE as f32 + p.offset.1 as f32,
+                        x: origin.x + p.offset.0 as f32 * scale,
+                        y: origin.y + p.offset.1 as f32 * scale,
                     },
-                    10.0,
+                    10.0 * scale,
                 ),
-                p.color.clone(),
-            ));
+                p.color,
+            );
+            // players waiting to enter are drawn hollow on the first tile
+            if state.on_board {
+                v_player.push(token);
+            } else {
+                v_waiting.push(token);
+            }
         }
 
         for (p, c) in v_player {
             frame.fill(&p, c);
         }
+        for (p, c) in v_waiting {
+            frame.stroke(
+                &p,
+                Stroke {
+                    style: canvas::Style::Solid(c),
+                    width: 3.0,
+                    ..Default::default()
+                },
+            );
+        }
         vec![frame.into_geometry()]
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 struct Player {
     name: String,
-    position: Position,
+    #[serde(with = "rgba")]
     color: Color,
     offset: (i32, i32),
+    team: usize,
+    handicap: Handicap,
 }
 
-struct GamePage {
-    game_type: Mode,
-    dice_value: u8,
+/// Colors are saved as `[r, g, b, a]` bytes.
+mod rgba {
+    use iced::Color;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(color: &Color, serializer: S) -> Result<S::Ok, S::Error> {
+        color.into_rgba8().serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Color, D::Error> {
+        let [r, g, b, a] = <[u8; 4]>::deserialize(deserializer)?;
+        Ok(Color::from_rgba8(r, g, b, f32::from(a) / 255.0))
+    }
+}
+
+/// A throw waiting for the current player to pick the die to move by.
+struct PendingChoice {
+    faces: Vec<u8>,
+    /// What moving by each die would do, in the order of `faces`.
+    previews: Vec<TurnOutcome>,
+}
+
+impl PendingChoice {
+    /// Tile the player lands on before any snake or ladder.
+    fn landed(preview: &TurnOutcome) -> Position {
+        preview
+            .events
+            .iter()
+            .find_map(|e| match e {
+                Event::Snake { from, .. } | Event::Ladder { from, .. } => Some(*from),
+                _ => None,
+            })
+            .unwrap_or(preview.to)
+    }
+
+    /// Die whose destination is the tile at `pos`.
+    fn die_at(&self, pos: Position) -> Option<usize> {
+        self.previews
+            .iter()
+            .position(|o| o.to == pos || PendingChoice::landed(o) == pos)
+    }
+}
+
+/// The game between two moves, what undo and redo go back and forth between.
+struct Snapshot {
+    state: GameState,
+    last_outcome: Option<TurnOutcome>,
+}
+
+/// File the game in progress is saved to after every move.
+const SAVE_FILE: &str = "snakes_n_ladders.save.json";
+
+/// Version of [`SavedGame`], bumped when older saves can no longer be read.
+const SAVE_VERSION: u32 = 1;
+
+/// A game in progress as written to [`SAVE_FILE`]. The undo history is not
+/// kept.
+#[derive(Serialize, Deserialize)]
+struct SavedGame {
+    version: u32,
+    seed: u64,
+    dice_kind: DiceKind,
+    /// State of seeded dice, so the game goes on with the same rolls.
+    dice_rng: Option<GameRng>,
+    dice_values: Vec<u8>,
+    /// Faces of a throw still waiting for the player to pick a die.
+    pending_pick: Option<Vec<u8>>,
+    last_outcome: Option<TurnOutcome>,
+    players: Vec<Player>,
+    state: GameState,
+    turn_limit: Option<u32>,
+    /// Players as the game started and every move since, for the replay.
+    #[serde(default)]
+    start: Vec<engine::Player>,
+    #[serde(default)]
+    turns: Vec<ReplayTurn>,
+    /// Where the replay of the game goes, see [`new_replay_file`].
+    #[serde(default)]
+    replay_file: String,
+}
+
+/// Folder the moves of every game are logged to, a file per game.
+const REPLAY_DIR: &str = "replays";
+
+/// Replay file of the game started last, if there is one.
+fn latest_replay() -> Option<String> {
+    std::fs::read_dir(REPLAY_DIR)
+        .ok()?
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .max()
+        .map(|path| path.display().to_string())
+}
+
+/// Replay file for a game with `seed` starting now, named by the start time
+/// first so that the names sort by it.
+fn new_replay_file(seed: u64) -> String {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    format!("{REPLAY_DIR}/{now}-{seed}.json")
+}
+
+/// Version of [`Replay`], bumped when older replays can no longer be read.
+const REPLAY_VERSION: u32 = 1;
+
+/// A game move by move, as written to its [`new_replay_file`].
+#[derive(Serialize, Deserialize)]
+struct Replay {
+    version: u32,
+    seed: u64,
     board: Board,
+    rules: Rules,
+    players: Vec<Player>,
+    /// Where everyone stood before the first move.
+    start: Vec<engine::Player>,
+    turns: Vec<ReplayTurn>,
+}
+
+/// One move and the game right after it.
+#[derive(Clone, Serialize, Deserialize)]
+struct ReplayTurn {
+    outcome: TurnOutcome,
+    players: Vec<engine::Player>,
     player_turn: usize,
+    team_places: Vec<usize>,
     ended: bool,
 }
 
+/// Writes `value` next to `path` and renames it, so a crash mid-write keeps
+/// the previous file.
+fn write_json(path: &str, value: &impl Serialize) -> std::io::Result<()> {
+    let text = serde_json::to_string(value).map_err(std::io::Error::other)?;
+    let temp = format!("{path}.tmp");
+    std::fs::write(&temp, text)?;
+    std::fs::rename(temp, path)
+}
+
+struct GamePage {
+    dice_values: Vec<u8>,
+    pending_pick: Option<PendingChoice>,
+    last_outcome: Option<TurnOutcome>,
+    seed: u64,
+    dice_kind: DiceKind,
+    dice: Box<dyn DiceSource>,
+    players: Vec<Player>,
+    state: GameState,
+    /// Earlier points of the game, the latest last.
+    undo: Vec<Snapshot>,
+    /// Points that were undone, redoing restores them with the same rolls.
+    redo: Vec<Snapshot>,
+    turn_limit: Option<u32>,
+    /// Seconds left before the game rolls for the current player.
+    time_left: u32,
+    /// Odds of the board, worked out in the background when the game starts.
+    analysis: Option<Analysis>,
+    /// Throws of the dice, when they were listed for generating the board.
+    throws: Option<Throws>,
+    /// Why the last autosave failed.
+    save_error: Option<String>,
+    /// Players as the game started, the replay begins from them.
+    start: Vec<engine::Player>,
+    /// Moves still standing after undo, logged for the replay.
+    turns: Vec<ReplayTurn>,
+    replay_file: String,
+}
+
 impl GamePage {
-    fn new(config: Config) -> Self {
+    fn new(mut config: Config) -> Self {
+        let (_, dice_rng, rng) = engine::seeded_rngs(config.seed);
+        let board = match config.board.take() {
+            Some(board) => board,
+            None => config.generate_board(),
+        };
+        let players: Vec<_> = config
+            .players
+            .iter()
+            .map(|p| engine::Player {
+                team: p.team,
+                handicap: p.handicap,
+                ..engine::Player::new(p.name.clone())
+            })
+            .collect();
+        let state = GameState::new(board, players, config.rules, rng);
         GamePage {
-            game_type: config.game_type,
-            dice_value: 0,
-            board: Board::new(config.players),
-            player_turn: 0 as usize,
-            ended: false,
+            dice_values: Vec::new(),
+            pending_pick: None,
+            last_outcome: None,
+            seed: config.seed,
+            dice_kind: config.dice_source,
+            dice: config.dice_source.source(dice_rng),
+            start: state.players.clone(),
+            turns: Vec::new(),
+            replay_file: new_replay_file(config.seed),
+            state,
+            players: config.players,
+            undo: Vec::new(),
+            redo: Vec::new(),
+            turn_limit: config.turn_limit,
+            time_left: config.turn_limit.unwrap_or_default(),
+            analysis: None,
+            throws: config.throws,
+            save_error: None,
         }
     }
 
+    /// Picks up the game saved in [`SAVE_FILE`].
+    fn resume() -> Result<Self, String> {
+        let text = std::fs::read_to_string(SAVE_FILE)
+            .map_err(|err| format!("cannot read the saved game: {err}"))?;
+        let saved: SavedGame = serde_json::from_str(&text)
+            .map_err(|err| format!("the saved game is damaged: {err}"))?;
+        if saved.version != SAVE_VERSION {
+            return Err(String::from(
+                "the saved game is from another version of the game",
+            ));
+        }
+        let dice_rng = saved
+            .dice_rng
+            .unwrap_or_else(|| engine::seeded_rngs(saved.seed).1);
+        let start = if saved.start.is_empty() {
+            saved.state.players.clone()
+        } else {
+            saved.start
+        };
+        let pending_pick = saved.pending_pick.map(|faces| PendingChoice {
+            previews: (0..faces.len())
+                .map(|i| saved.state.preview_dice(&faces, Some(i)))
+                .collect(),
+            faces,
+        });
+        Ok(GamePage {
+            dice_values: saved.dice_values,
+            pending_pick,
+            last_outcome: saved.last_outcome,
+            seed: saved.seed,
+            dice_kind: saved.dice_kind,
+            dice: saved.dice_kind.source(dice_rng),
+            players: saved.players,
+            analysis: None,
+            throws: None,
+            state: saved.state,
+            undo: Vec::new(),
+            redo: Vec::new(),
+            turn_limit: saved.turn_limit,
+            time_left: saved.turn_limit.unwrap_or_default(),
+            save_error: None,
+            start,
+            turns: saved.turns,
+            replay_file: if saved.replay_file.is_empty() {
+                new_replay_file(saved.seed)
+            } else {
+                saved.replay_file
+            },
+        })
+    }
+
+    fn save(&self) -> std::io::Result<()> {
+        let saved = SavedGame {
+            version: SAVE_VERSION,
+            seed: self.seed,
+            dice_kind: self.dice_kind,
+            dice_rng: self.dice.rng().cloned(),
+            dice_values: self.dice_values.clone(),
+            pending_pick: self.pending_pick.as_ref().map(|p| p.faces.clone()),
+            last_outcome: self.last_outcome.clone(),
+            players: self.players.clone(),
+            state: self.state.clone(),
+            turn_limit: self.turn_limit,
+            start: self.start.clone(),
+            turns: self.turns.clone(),
+            replay_file: self.replay_file.clone(),
+        };
+        write_json(SAVE_FILE, &saved)?;
+        let replay = Replay {
+            version: REPLAY_VERSION,
+            seed: self.seed,
+            board: self.state.board.clone(),
+            rules: self.state.rules,
+            players: self.players.clone(),
+            start: self.start.clone(),
+            turns: self.turns.clone(),
+        };
+        std::fs::create_dir_all(REPLAY_DIR)?;
+        write_json(&self.replay_file, &replay)
+    }
+
+    fn autosave(&mut self) {
+        self.save_error = self.save().err().map(|err| err.to_string());
+    }
+
     fn update(&mut self, message: GameMsg) {
         match message {
-            GameMsg::RollDice => {
+            GameMsg::RollDice => self.game_logic(),
+            GameMsg::EnterRoll(value) => {
+                self.dice.enter(value);
                 self.game_logic();
             }
-            _ => (),
+            GameMsg::PickDie(i) => {
+                if let Some(choice) = self.pending_pick.take() {
+                    let before = self.snapshot();
+                    let outcome = self.state.apply_dice(&choice.faces, Some(i));
+                    self.record(before, outcome);
+                }
+            }
+            GameMsg::PlayCard(player, card) => {
+                let before = self.snapshot();
+                if let Some(outcome) = self.state.play_card(player, card) {
+                    self.dice_values.clear();
+                    self.record(before, outcome);
+                }
+            }
+            GameMsg::Undo => {
+                // a throw waiting for a pick is dropped first
+                if self.pending_pick.take().is_some() {
+                    self.dice_values = self.shown_dice();
+                } else if let Some(snapshot) = self.undo.pop() {
+                    self.redo.push(self.snapshot());
+                    self.restore(snapshot);
+                    self.turns.pop();
+                }
+            }
+            GameMsg::Redo => {
+                if let Some(snapshot) = self.redo.pop() {
+                    self.pending_pick = None;
+                    self.undo.push(self.snapshot());
+                    self.restore(snapshot);
+                    self.log_turn();
+                }
+            }
+            GameMsg::Tick => return self.tick(),
+            // the clipboard is written by the app
+            GameMsg::CopyCode => return,
+            GameMsg::GoToMenu => (),
+        }
+        // whatever the players did, the clock starts over
+        self.time_left = self.turn_limit.unwrap_or_default();
+        self.autosave();
+    }
+
+    /// Whether the turn timer is counting down.
+    fn timer_running(&self) -> bool {
+        self.turn_limit.is_some() && !self.state.ended
+    }
+
+    fn tick(&mut self) {
+        if !self.timer_running() {
+            return;
+        }
+        self.time_left = self.time_left.saturating_sub(1);
+        if self.time_left == 0 {
+            self.auto_roll();
+            self.time_left = self.turn_limit.unwrap_or_default();
+            self.autosave();
+        }
+    }
+
+    /// Plays the turn of a player who ran out of time: a pending throw moves
+    /// by the highest die and physical dice are replaced by random ones.
+    fn auto_roll(&mut self) {
+        let before = self.snapshot();
+        let outcome = if let Some(choice) = self.pending_pick.take() {
+            let pick = self.state.auto_pick(&choice.faces);
+            Some(self.state.apply_dice(&choice.faces, pick))
+        } else if self.dice.is_manual() {
+            self.dice.clear();
+            self.state.play_turn(&mut RandomDice)
+        } else {
+            self.state.play_turn(self.dice.as_mut())
+        };
+        if let Some(outcome) = outcome {
+            self.dice_values = outcome.dice.clone();
+            self.record(before, outcome);
         }
     }
 
     fn view(&self) -> Element<'_, GameMsg> {
-        row![
-            Container::new(
-                widget::canvas(self.board.clone())
-                    .width(Length::Fixed(1000 as f32))
-                    .height(Length::Fixed(1000 as f32))
-            )
-            .align_x(Center)
-            .align_y(Center)
-            .height(Length::Fill)
-            .width(Length::FillPortion(12)),
-            widget::Rule::vertical(2),
-            column![
-                self.status(),
-                widget::Rule::horizontal(4),
-                self.dice(),
-                widget::Rule::horizontal(2),
-                self.ranking(),
-                widget::Rule::horizontal(2),
+        let mut side = column![
+            self.status(),
+            self.code(),
+            widget::Rule::horizontal(4),
+            self.dice(),
+            widget::Rule::horizontal(2),
+        ];
+        if self.state.rules.cards {
+            side = side.push(self.cards()).push(widget::Rule::horizontal(2));
+        }
+        let side = side
+            .push(self.ranking())
+            .push(widget::Rule::horizontal(2))
+            .push(
                 Container::new(
                     widget::button(
                         widget::text("Exit to main Menu")
                             .align_x(Center)
                             .align_y(Center)
                             .width(Fill)
-                            .size(30)
+                            .size(30),
                     )
                     .on_press(GameMsg::GoToMenu)
-                    .width(Length::Fill)
+                    .width(Length::Fill),
                 )
                 .padding(50)
                 .center_x(Length::Fill),
-            ]
+            );
+        row![
+            Container::new(
+                widget::canvas(self)
+                    .width(Length::Fixed(
+                        self.state.board.width as f32 * self.tile_size()
+                    ))
+                    .height(Length::Fixed(
+                        self.state.board.height as f32 * self.tile_size()
+                    ))
+            )
+            .align_x(Center)
+            .align_y(Center)
             .height(Length::Fill)
-            .width(Length::FillPortion(4)),
+            .width(Length::FillPortion(12)),
+            widget::Rule::vertical(2),
+            side.height(Length::Fill).width(Length::FillPortion(4)),
         ]
         .into()
     }
 
     fn status(&self) -> Container<'_, GameMsg> {
         Container::new(column![
-            Container::new(column![
-                Container::new(widget::text("Status").size(30))
-                    .padding(10)
+            Container::new(
+                column![
+                    Container::new(widget::text("Status").size(30))
+                        .padding(10)
+                        .align_x(Center)
+                        .width(Length::Fill),
+                    widget::Rule::horizontal(4),
+                    Container::new(widget::column(
+                        self.notes().into_iter().map(|n| widget::text(n).into())
+                    ))
+                    .padding(5)
                     .align_x(Center)
                     .width(Length::Fill),
-                widget::Rule::horizontal(4),
-                Container::new(widget::text("next player's turn"))
+                    Container::new(widget::text(format!("Seed: {}", self.seed)))
+                        .padding(5)
+                        .align_x(Center)
+                        .width(Length::Fill),
+                    Container::new(widget::text(match &self.analysis {
+                        Some(odds) => odds.summary(),
+                        None => String::from("Working out the odds of the board..."),
+                    }))
                     .padding(5)
                     .align_x(Center)
                     .width(Length::Fill),
-            ])
+                ]
+                .push_maybe(self.timer_running().then(|| {
+                    Container::new(
+                        widget::text(format!("Time left: {} s", self.time_left)).size(24),
+                    )
+                    .padding(5)
+                    .align_x(Center)
+                    .width(Length::Fill)
+                }))
+            )
             .style(|_| Style {
                 background: Some(Background::Color(Color::from_rgb8(0, 0, 0))),
                 border: Border {
                     color: Color::WHITE,
-                    width: 2 as f32,
+                    width: 2.0,
                     radius: Radius::new(20),
                 },
                 ..Default::default()
@@ -628,26 +1704,138 @@ impl GamePage {
         .center_x(Length::Fill)
     }
 
+    /// Task working out the odds of the board, which takes seconds on big
+    /// boards.
+    fn analyze(&self) -> Task<Message> {
+        let board = self.state.board.clone();
+        let rules = self.state.rules;
+        let throws = self
+            .throws
+            .clone()
+            .filter(|throws| *throws.rules() == rules);
+        let code = self.board_code();
+        Task::perform(
+            blocking(move || {
+                let throws = throws.unwrap_or_else(|| Throws::new(&rules));
+                analysis::analyze_with(&board, &throws)
+            }),
+            move |odds| Message::Analyzed(code.clone(), odds),
+        )
+    }
+
+    /// Keeps the odds from [`analyze`](Self::analyze), unless they are for
+    /// the board of an earlier game.
+    fn set_analysis(&mut self, code: &str, odds: Analysis) {
+        if code == self.board_code() {
+            self.analysis = Some(odds);
+        }
+    }
+
+    fn board_code(&self) -> String {
+        board_code::encode(&self.state.board, &self.state.rules)
+    }
+
+    /// The board code to share the board with, and a button to copy it.
+    fn code(&self) -> Container<'_, GameMsg> {
+        Container::new(
+            column![
+                widget::text(format!("Board code: {}", self.board_code())).size(16),
+                widget::button("Copy").on_press(GameMsg::CopyCode),
+            ]
+            .spacing(5)
+            .align_x(Center),
+        )
+        .padding(10)
+        .center_x(Length::Fill)
+    }
+
     fn dice(&self) -> Container<'_, GameMsg> {
-        Container::new(column![
-            Container::new(
-                widget::text(self.dice_value)
-                    .size(100)
-                    .align_x(Center)
-                    .align_y(Center)
-                    .width(Fill)
-            ),
-            Container::new(
+        let dice = &self.state.rules.dice;
+        let roll: Element<GameMsg> = if let Some(choice) = &self.pending_pick {
+            widget::row(choice.faces.iter().enumerate().map(|(i, &face)| {
+                let to = self.state.board.index(choice.previews[i].to) + 1;
                 widget::button(
-                    widget::text("Roll Dice")
+                    widget::text(format!("{} to {}", face, to))
                         .size(30)
                         .align_x(Center)
                         .align_y(Center)
-                        .width(Fill)
+                        .width(Fill),
                 )
-                .on_press(GameMsg::RollDice)
+                .on_press(GameMsg::PickDie(i))
                 .width(Length::Fill)
+                .into()
+            }))
+            .spacing(5)
+            .into()
+        } else if self.dice.is_manual() {
+            let faces: Vec<u8> = (1..=dice.sides).collect();
+            widget::column(faces.chunks(6).map(|chunk| {
+                widget::row(chunk.iter().map(|&v| {
+                    widget::button(
+                        widget::text(v)
+                            .size(30)
+                            .align_x(Center)
+                            .align_y(Center)
+                            .width(Fill),
+                    )
+                    .on_press(GameMsg::EnterRoll(v))
+                    .width(Length::Fill)
+                    .into()
+                }))
+                .spacing(5)
+                .into()
+            }))
+            .spacing(5)
+            .into()
+        } else {
+            widget::button(
+                widget::text("Roll Dice")
+                    .size(30)
+                    .align_x(Center)
+                    .align_y(Center)
+                    .width(Fill),
             )
+            .on_press(GameMsg::RollDice)
+            .width(Length::Fill)
+            .into()
+        };
+        // manual entries of an unfinished throw replace the last roll
+        let shown = if self.dice.entered().is_empty() {
+            &self.dice_values[..]
+        } else {
+            self.dice.entered()
+        };
+        let values = if shown.is_empty() {
+            String::from("0")
+        } else {
+            shown
+                .iter()
+                .map(|v| v.to_string())
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        Container::new(column![
+            Container::new(
+                widget::text(values)
+                    .size(if dice.count > 1 { 60 } else { 100 })
+                    .align_x(Center)
+                    .align_y(Center)
+                    .width(Fill)
+            ),
+            Container::new(roll),
+            row![
+                widget::button(widget::text("Undo").align_x(Center).width(Fill))
+                    .on_press_maybe(
+                        (!self.undo.is_empty() || self.pending_pick.is_some())
+                            .then_some(GameMsg::Undo)
+                    )
+                    .width(Length::Fill),
+                widget::button(widget::text("Redo").align_x(Center).width(Fill))
+                    .on_press_maybe((!self.redo.is_empty()).then_some(GameMsg::Redo))
+                    .width(Length::Fill),
+            ]
+            .spacing(5)
+            .padding([10, 0]),
         ])
         .padding(50)
         .align_x(Center)
@@ -655,56 +1843,159 @@ impl GamePage {
         .width(Length::Fill)
     }
 
-    fn ranking(&self) -> Container<'_, GameMsg> {
-        let mut ranks: Vec<&Player> = self.board.players.iter().collect();
-        ranks.sort_by_key(|p| -(p.position.y * 10 + p.position.x + 10));
-
-        let r_list: Vec<Element<GameMsg>> = ranks
+    /// Cards in every player's hand, the ones that can be played right now
+    /// are enabled.
+    fn cards(&self) -> Container<'_, GameMsg> {
+        let hands = self
+            .players
             .iter()
+            .zip(self.state.players.iter())
             .enumerate()
-            .map(|(i, p)| {
-                Container::new(column![
-                    widget::Rule::horizontal(4),
-                    row![
-                        widget::text(i + 1)
-                            .size(20)
-                            .width(Length::FillPortion(2))
-                            .align_x(Center)
-                            .align_y(Center),
-                        widget::text(&p.name)
-                            .size(20)
-                            .width(Length::FillPortion(6))
-                            .align_x(Center)
-                            .align_y(Center),
-                        Container::new(
-                            Container::new("")
-                                .width(Length::Fixed(16.0))
-                                .height(Length::Fixed(16.0))
-                                .style(|_| {
-                                    Style {
-                                        background: Some(Background::Color(p.color.clone())),
-                                        ..Default::default()
-                                    }
-                                })
-                        )
+            .filter(|(_, (_, state))| !state.cards.is_empty())
+            .map(|(i, (p, state))| {
+                row![
+                    widget::text(p.name.clone())
+                        .size(20)
+                        .width(Length::FillPortion(2)),
+                    widget::row(state.cards.iter().map(|&card| {
+                        let playable = self.pending_pick.is_none() && self.state.can_play(i, card);
+                        widget::button(widget::text(card.to_string()).align_x(Center).width(Fill))
+                            .on_press_maybe(playable.then_some(GameMsg::PlayCard(i, card)))
+                            .width(Length::Fill)
+                            .into()
+                    }))
+                    .spacing(5)
+                    .width(Length::FillPortion(6)),
+                ]
+                .spacing(10)
+                .align_y(Center)
+                .into()
+            });
+        Container::new(column![
+            Container::new(widget::text("Cards").size(30))
+                .padding(10)
+                .align_x(Center)
+                .width(Length::Fill),
+            widget::column(hands).spacing(5).padding(5),
+        ])
+        .width(Length::Fill)
+    }
+
+    fn ranking(&self) -> Container<'_, GameMsg> {
+        let mut ranks: Vec<(&Player, &engine::Player)> =
+            self.players.iter().zip(self.state.players.iter()).collect();
+        // finished players in finishing order, then the rest by tile
+        ranks.sort_by_key(|(_, state)| {
+            (
+                state.place.unwrap_or(usize::MAX),
+                !state.on_board,
+                -self.state.board.index(state.position),
+            )
+        });
+
+        let player_row = |rank: String, p: &Player, state: &engine::Player| -> Element<GameMsg> {
+            Container::new(column![
+                widget::Rule::horizontal(4),
+                row![
+                    widget::text(rank)
+                        .size(20)
                         .width(Length::FillPortion(2))
-                        .align_x(Center),
-                        widget::text(p.position.y * 10 + p.position.x + 1)
-                            .align_x(Center)
-                            .align_y(Center)
-                            .size(20)
-                            .width(Length::FillPortion(2)),
-                    ]
-                    .padding(7)
+                        .align_x(Center)
+                        .align_y(Center),
+                    widget::text(p.name.clone())
+                        .size(20)
+                        .width(Length::FillPortion(6))
+                        .align_x(Center)
+                        .align_y(Center),
+                    Container::new(
+                        Container::new("")
+                            .width(Length::Fixed(16.0))
+                            .height(Length::Fixed(16.0))
+                            .style({
+                                let color = p.color;
+                                move |_| Style {
+                                    background: Some(Background::Color(color)),
+                                    ..Default::default()
+                                }
+                            })
+                    )
+                    .width(Length::FillPortion(2))
+                    .align_x(Center),
+                    widget::text(if state.on_board {
+                        (self.state.board.index(state.position) + 1).to_string()
+                    } else {
+                        String::from("-")
+                    })
+                    .align_x(Center)
                     .align_y(Center)
-                ])
+                    .size(20)
+                    .width(Length::FillPortion(2)),
+                    widget::text(state.turns)
+                        .align_x(Center)
+                        .align_y(Center)
+                        .size(20)
+                        .width(Length::FillPortion(2)),
+                ]
+                .padding(7)
                 .align_y(Center)
-                .align_x(Center)
-                .center_y(Length::Shrink)
-                .width(Length::Fill)
-                .into()
-            })
-            .collect();
+            ])
+            .align_y(Center)
+            .align_x(Center)
+            .center_y(Length::Shrink)
+            .width(Length::Fill)
+            .into()
+        };
+
+        let r_list: Vec<Element<GameMsg>> = if self.state.rules.teams.is_some() {
+            let mut teams = self.state.teams();
+            teams.sort_by_key(|&t| {
+                (
+                    self.state.team_place(t).unwrap_or(usize::MAX),
+                    -self.state.team_score(t),
+                )
+            });
+            let mut list = Vec::new();
+            for (i, team) in teams.into_iter().enumerate() {
+                list.push(
+                    Container::new(column![
+                        widget::Rule::horizontal(4),
+                        row![
+                            widget::text(i + 1)
+                                .size(20)
+                                .width(Length::FillPortion(2))
+                                .align_x(Center),
+                            widget::text(format!("Team {}", team + 1))
+                                .size(20)
+                                .width(Length::FillPortion(8))
+                                .align_x(Center),
+                            widget::text(format!("Score {}", self.state.team_score(team)))
+                                .size(20)
+                                .width(Length::FillPortion(4))
+                                .align_x(Center),
+                        ]
+                        .padding(7)
+                        .align_y(Center)
+                    ])
+                    .style(|_| Style {
+                        background: Some(Background::Color(Color::from_rgb8(40, 40, 60))),
+                        ..Default::default()
+                    })
+                    .width(Length::Fill)
+                    .into(),
+                );
+                for (p, state) in ranks.iter().filter(|(_, state)| state.team == team) {
+                    let place = state.place.map(|p| p.to_string()).unwrap_or_default();
+                    list.push(player_row(place, p, state));
+                }
+            }
+            list
+        } else {
+            ranks
+                .iter()
+                .enumerate()
+                .map(|(i, (p, state))| player_row((i + 1).to_string(), p, state))
+                .collect()
+        };
         let r_list = widget::column(r_list);
         Container::new(column![
             Container::new(column![
@@ -740,6 +2031,11 @@ impl GamePage {
                             .align_y(Center)
                             .size(20)
                             .width(Length::FillPortion(2)),
+                        widget::text("Turns")
+                            .align_x(Center)
+                            .align_y(Center)
+                            .size(20)
+                            .width(Length::FillPortion(2)),
                     ]
                     .padding(7)
                     .align_y(Center)
@@ -754,7 +2050,7 @@ impl GamePage {
                 background: Some(Background::Color(Color::from_rgb8(0, 0, 0))),
                 border: Border {
                     color: Color::WHITE,
-                    width: 2 as f32,
+                    width: 2.0,
                     radius: Radius::new(20),
                 },
                 ..Default::default()
@@ -770,70 +2066,314 @@ impl GamePage {
         .center_x(Length::Fill)
     }
 
-    fn roll_dice() -> u8 {
-        rand::thread_rng().gen_range(1..=6) as u8
-    }
-
     fn game_logic(&mut self) {
-        if self.ended {
-            return ();
-        }
-        self.dice_value = GamePage::roll_dice();
-        let total_players = self.board.players.len();
-        let (left, right) = self.board.players.split_at_mut(self.player_turn);
-        let (current_player, right) = right.split_at_mut(1);
-        let current_player = &mut current_player[0];
-        let pos = current_player.position.y * 10 + current_player.position.x;
-        let new_pos = pos + self.dice_value as i32;
-        if self.dice_value != 6 {
-            self.player_turn += 1;
-            if self.player_turn == total_players {
-                self.player_turn = 0;
-            }
-        }
-        if new_pos > 99 {
-            return ();
-        } else if new_pos == 99 {
-            self.ended = true;
-        }
-        let old_position = current_player.position;
-        match self.board.tile[(new_pos / 10) as usize][(new_pos % 10) as usize] {
-            Tile::Snake(pos) | Tile::Ladder(pos) => current_player.position = pos,
-            _ => {
-                current_player.position = Position {
-                    x: new_pos % 10,
-                    y: new_pos / 10,
-                };
+        if self.state.ended || self.pending_pick.is_some() {
+            return;
+        }
+        if self.state.rules.dice.needs_pick() {
+            if let Some(faces) = self.dice.roll(&self.state.rules.dice) {
+                let previews = (0..faces.len())
+                    .map(|i| self.state.preview_dice(&faces, Some(i)))
+                    .collect();
+                self.dice_values = faces.clone();
+                self.pending_pick = Some(PendingChoice { faces, previews });
+            }
+        } else {
+            let before = self.snapshot();
+            if let Some(outcome) = self.state.play_turn(self.dice.as_mut()) {
+                self.dice_values = outcome.dice.clone();
+                self.record(before, outcome);
             }
         }
+    }
 
-        match self.game_type {
-            Mode::Bump => {
-                for p in left.iter_mut().chain(right.iter_mut()) {
-                    if current_player.position == p.position {
-                        p.position = Position { x: 0, y: 0 };
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            state: self.state.clone(),
+            last_outcome: self.last_outcome.clone(),
+        }
+    }
+
+    /// Remembers the game from right before a move and logs the move for
+    /// the replay. The move makes the undone ones unreachable.
+    fn record(&mut self, before: Snapshot, outcome: TurnOutcome) {
+        self.undo.push(before);
+        self.redo.clear();
+        self.last_outcome = Some(outcome);
+        self.log_turn();
+    }
+
+    /// Adds the last move and where it left everyone to the replay.
+    fn log_turn(&mut self) {
+        if let Some(outcome) = &self.last_outcome {
+            self.turns.push(ReplayTurn {
+                outcome: outcome.clone(),
+                players: self.state.players.clone(),
+                player_turn: self.state.player_turn,
+                team_places: self.state.team_places.clone(),
+                ended: self.state.ended,
+            });
+        }
+    }
+
+    fn restore(&mut self, snapshot: Snapshot) {
+        self.state = snapshot.state;
+        self.last_outcome = snapshot.last_outcome;
+        self.dice_values = self.shown_dice();
+    }
+
+    /// Dice of the last move, shown while nothing else was thrown.
+    fn shown_dice(&self) -> Vec<u8> {
+        self.last_outcome
+            .as_ref()
+            .map(|outcome| outcome.dice.clone())
+            .unwrap_or_default()
+    }
+
+    /// Lines for the status panel: what the last roll did and who is next.
+    fn notes(&self) -> Vec<String> {
+        let name = |i: usize| &self.state.players[i].name;
+        let mut notes = Vec::new();
+        if let Some(outcome) = &self.last_outcome {
+            for event in &outcome.events {
+                notes.push(match event {
+                    Event::Overshoot => format!("{} overshot the last tile", name(outcome.player)),
+                    Event::Bounced => format!("{} bounced back", name(outcome.player)),
+                    Event::Entered => format!("{} entered the board", name(outcome.player)),
+                    Event::NotEntered => {
+                        format!("{} is still waiting to enter", name(outcome.player))
+                    }
+                    Event::Snake { .. } => {
+                        format!("{} was bitten by a snake", name(outcome.player))
+                    }
+                    Event::Ladder { .. } => format!("{} climbed a ladder", name(outcome.player)),
+                    Event::Bumped(i) => format!("{} was bumped to the start", name(*i)),
+                    Event::Swapped(i) => format!("{} was swapped back", name(*i)),
+                    Event::Finished(place) => {
+                        format!("{} finished in place {}", name(outcome.player), place)
+                    }
+                    Event::TeamFinished { team, place } => {
+                        format!("Team {} finished in place {}", team + 1, place)
+                    }
+                    Event::Special(Special::SkipTurn) => {
+                        format!("{} will lose the next turn", name(outcome.player))
                     }
+                    Event::Special(Special::ExtraRoll) => {
+                        format!("{} rolls again", name(outcome.player))
+                    }
+                    Event::Special(Special::Teleport) => {
+                        format!("{} was teleported", name(outcome.player))
+                    }
+                    Event::Special(Special::Reverse) => {
+                        format!("{} will move backwards next turn", name(outcome.player))
+                    }
+                    Event::Reversed => format!("{} moved backwards", name(outcome.player)),
+                    Event::Skipped(i) => format!("{} loses the turn", name(*i)),
+                    Event::CardEarned(card) => {
+                        format!("{} earned a card: {}", name(outcome.player), card)
+                    }
+                    Event::CardPlayed(Card::ReRoll) => {
+                        format!("{} takes back the roll", name(outcome.player))
+                    }
+                    Event::CardPlayed(card) => format!("{} played {}", name(outcome.player), card),
+                    Event::Shielded => {
+                        format!("{} was shielded from a snake", name(outcome.player))
+                    }
+                    Event::ThreeSixes => match self.state.rules.three_sixes {
+                        ThreeSixesRule::BackToStart => format!(
+                            "{} earned a third extra roll and goes back to the start of the turn",
+                            name(outcome.player)
+                        ),
+                        _ => format!(
+                            "{} earned a third extra roll and forfeits the move",
+                            name(outcome.player)
+                        ),
+                    },
+                });
+            }
+        }
+        if self.state.ended {
+            notes.push(String::from("Game over"));
+        } else {
+            notes.push(format!("{}'s turn", name(self.state.player_turn)));
+        }
+        if let Some(err) = &self.save_error {
+            notes.push(format!("The game could not be saved: {err}"));
+        }
+        notes
+    }
+}
+
+/// A recorded game, stepped through move by move on the board of a
+/// [`GamePage`] that is only drawn.
+struct ReplayPage {
+    start: Vec<engine::Player>,
+    turns: Vec<ReplayTurn>,
+    /// Moves shown so far, 0 is the start of the game.
+    step: usize,
+    page: GamePage,
+    /// Moving on by a move every second.
+    playing: bool,
+}
+
+impl ReplayPage {
+    /// Opens the replay written to `path`.
+    fn load(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|err| format!("cannot read the replay: {err}"))?;
+        let replay: Replay =
+            serde_json::from_str(&text).map_err(|err| format!("the replay is damaged: {err}"))?;
+        if replay.version != REPLAY_VERSION {
+            return Err(String::from(
+                "the replay is from another version of the game",
+            ));
+        }
+        let page = GamePage {
+            dice_values: Vec::new(),
+            pending_pick: None,
+            last_outcome: None,
+            seed: replay.seed,
+            dice_kind: DiceKind::default(),
+            dice: Box::new(RandomDice),
+            players: replay.players,
+            analysis: None,
+            throws: None,
+            state: GameState::new(
+                replay.board,
+                replay.start.clone(),
+                replay.rules,
+                engine::seeded_rngs(replay.seed).2,
+            ),
+            undo: Vec::new(),
+            redo: Vec::new(),
+            turn_limit: None,
+            time_left: 0,
+            save_error: None,
+            start: Vec::new(),
+            turns: Vec::new(),
+            replay_file: path.to_string(),
+        };
+        let mut replay = ReplayPage {
+            start: replay.start,
+            turns: replay.turns,
+            step: 0,
+            page,
+            playing: false,
+        };
+        replay.show(0);
+        Ok(replay)
+    }
+
+    /// Puts the game as it stood after `step` moves on the board.
+    fn show(&mut self, step: usize) {
+        self.step = step.min(self.turns.len());
+        let state = &mut self.page.state;
+        match self.step.checked_sub(1).map(|i| &self.turns[i]) {
+            Some(turn) => {
+                state.players = turn.players.clone();
+                state.player_turn = turn.player_turn;
+                state.team_places = turn.team_places.clone();
+                state.ended = turn.ended;
+                self.page.last_outcome = Some(turn.outcome.clone());
+            }
+            None => {
+                state.players = self.start.clone();
+                state.player_turn = 0;
+                state.team_places.clear();
+                state.ended = false;
+                self.page.last_outcome = None;
+            }
+        }
+        self.page.dice_values = self.page.shown_dice();
+    }
+
+    fn update(&mut self, message: ReplayMsg) {
+        match message {
+            ReplayMsg::First => self.show(0),
+            ReplayMsg::Back => self.show(self.step.saturating_sub(1)),
+            ReplayMsg::Forward => self.show(self.step + 1),
+            ReplayMsg::Last => self.show(self.turns.len()),
+            ReplayMsg::Jump(step) => self.show(step as usize),
+            ReplayMsg::TogglePlay => {
+                self.playing = !self.playing;
+                // playing a finished replay starts it over
+                if self.playing && self.step == self.turns.len() {
+                    self.show(0);
                 }
             }
-            Mode::Swap => {
-                if let Some(p) = left
-                    .iter_mut()
-                    .chain(right.iter_mut())
-                    .find(|i| i.position == current_player.position)
-                {
-                    p.position = old_position;
+            ReplayMsg::Tick => {
+                if self.step < self.turns.len() {
+                    self.show(self.step + 1);
+                } else {
+                    self.playing = false;
                 }
             }
-            _ => (),
+            ReplayMsg::Board | ReplayMsg::GoToMenu => (),
         }
     }
+
+    fn view(&self) -> Element<'_, ReplayMsg> {
+        let last = self.turns.len();
+        let controls = column![
+            widget::text(format!("Move {} of {}", self.step, last)).size(24),
+            row![
+                widget::button("|<").on_press_maybe((self.step > 0).then_some(ReplayMsg::First)),
+                widget::button("<").on_press_maybe((self.step > 0).then_some(ReplayMsg::Back)),
+                widget::button(if self.playing { "Pause" } else { "Play" })
+                    .on_press(ReplayMsg::TogglePlay),
+                widget::button(">")
+                    .on_press_maybe((self.step < last).then_some(ReplayMsg::Forward)),
+                widget::button(">|").on_press_maybe((self.step < last).then_some(ReplayMsg::Last)),
+            ]
+            .spacing(10),
+            widget::slider(0..=last as u32, self.step as u32, ReplayMsg::Jump),
+        ]
+        .spacing(10)
+        .padding(20)
+        .align_x(Center);
+        let side = column![
+            Element::from(self.page.status()).map(|_| ReplayMsg::Board),
+            widget::Rule::horizontal(4),
+            controls,
+            widget::Rule::horizontal(2),
+            Element::from(self.page.ranking()).map(|_| ReplayMsg::Board),
+            widget::Rule::horizontal(2),
+            Container::new(
+                widget::button(
+                    widget::text("Exit to main Menu")
+                        .align_x(Center)
+                        .align_y(Center)
+                        .width(Fill)
+                        .size(30),
+                )
+                .on_press(ReplayMsg::GoToMenu)
+                .width(Length::Fill),
+            )
+            .padding(50)
+            .center_x(Length::Fill),
+        ];
+        let board = &self.page.state.board;
+        let canvas = widget::canvas(&self.page)
+            .width(Length::Fixed(board.width as f32 * self.page.tile_size()))
+            .height(Length::Fixed(board.height as f32 * self.page.tile_size()));
+        row![
+            Container::new(Element::from(canvas).map(|_| ReplayMsg::Board))
+                .align_x(Center)
+                .align_y(Center)
+                .height(Length::Fill)
+                .width(Length::FillPortion(12)),
+            widget::Rule::vertical(2),
+            side.height(Length::Fill).width(Length::FillPortion(4)),
+        ]
+        .into()
+    }
 }
 
 fn main() -> iced::Result {
     let window_settings = Settings {
         size: iced::Size {
-            width: 320.0 as f32,
-            height: 400.0 as f32,
+            width: 320.0,
+            height: 400.0,
         },
         position: WindowPosition::Centered,
         ..Default::default()
@@ -841,6 +2381,7 @@ fn main() -> iced::Result {
     let app = iced::application("snakes_n_ladders", App::update, App::view);
     let app = app
         .window(window_settings)
-        .theme(|_state| iced::Theme::Dracula);
+        .theme(|_state| iced::Theme::Dracula)
+        .subscription(App::subscription);
     app.run()
 }
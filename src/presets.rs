@@ -0,0 +1,222 @@
+//! Fixed boards that ship with the game.
+//!
+//! Tiles are numbered as in a [board file](crate::board_file), from 1 in the
+//! bottom left corner up to the winning tile.
+
+use crate::{
+    board_file::{BoardFile, Link, SpecialTile},
+    engine::{Board, Special},
+};
+
+/// A named board layout, kept as plain data so it can be checked and
+/// analyzed like any other board.
+#[derive(Debug, PartialEq)]
+pub struct Preset {
+    pub name: &'static str,
+    pub author: &'static str,
+    pub width: usize,
+    pub height: usize,
+    pub snakes: &'static [Link],
+    pub ladders: &'static [Link],
+    pub safe: &'static [i32],
+    pub specials: &'static [SpecialTile],
+}
+
+impl Preset {
+    /// Lays the preset out on a board.
+    ///
+    /// # Panics
+    ///
+    /// If the preset is not a valid board, which none of [`PRESETS`] is.
+    pub fn board(&self) -> Board {
+        let file = BoardFile {
+            name: self.name.to_string(),
+            author: self.author.to_string(),
+            width: self.width,
+            height: self.height,
+            snakes: self.snakes.to_vec(),
+            ladders: self.ladders.to_vec(),
+            safe: self.safe.to_vec(),
+            specials: self.specials.to_vec(),
+        };
+        match Board::try_from(file) {
+            Ok(board) => board,
+            Err(err) => panic!("preset {} is broken: {err}", self.name),
+        }
+    }
+}
+
+impl std::fmt::Display for Preset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name)
+    }
+}
+
+const fn link(from: i32, to: i32) -> Link {
+    Link { from, to }
+}
+
+const fn special(tile: i32, effect: Special) -> SpecialTile {
+    SpecialTile { tile, effect }
+}
+
+/// The classic layout of Milton Bradley's Chutes and Ladders. The ladder on
+/// tile 1 only lifts players who land there, not the ones starting on it.
+pub const CHUTES_AND_LADDERS: Preset = Preset {
+    name: "Chutes and Ladders",
+    author: "Milton Bradley",
+    width: 10,
+    height: 10,
+    snakes: &[
+        link(16, 6),
+        link(47, 26),
+        link(49, 11),
+        link(56, 53),
+        link(62, 19),
+        link(64, 60),
+        link(87, 24),
+        link(93, 73),
+        link(95, 75),
+        link(98, 78),
+    ],
+    ladders: &[
+        link(1, 38),
+        link(4, 14),
+        link(9, 31),
+        link(21, 42),
+        link(28, 84),
+        link(36, 44),
+        link(51, 67),
+        link(71, 91),
+        link(80, 100),
+    ],
+    safe: &[],
+    specials: &[],
+};
+
+/// A short board for young players, mostly ladders.
+pub const LITTLE_STEPS: Preset = Preset {
+    name: "Little Steps",
+    author: "",
+    width: 8,
+    height: 8,
+    snakes: &[link(25, 14), link(47, 38), link(62, 53)],
+    ladders: &[
+        link(3, 12),
+        link(7, 22),
+        link(18, 29),
+        link(27, 41),
+        link(36, 50),
+        link(44, 58),
+    ],
+    safe: &[],
+    specials: &[],
+};
+
+/// Snakes crowd the last rows, only a few ladders help to get there.
+pub const GAUNTLET: Preset = Preset {
+    name: "Gauntlet",
+    author: "",
+    width: 10,
+    height: 10,
+    snakes: &[
+        link(43, 17),
+        link(54, 31),
+        link(62, 18),
+        link(87, 66),
+        link(91, 71),
+        link(93, 69),
+        link(94, 75),
+        link(96, 56),
+        link(99, 63),
+    ],
+    ladders: &[
+        link(2, 23),
+        link(8, 34),
+        link(20, 77),
+        link(32, 68),
+        link(41, 79),
+        link(74, 88),
+    ],
+    safe: &[],
+    specials: &[],
+};
+
+/// A big board for long evenings.
+pub const LONG_HAUL: Preset = Preset {
+    name: "Long Haul",
+    author: "",
+    width: 12,
+    height: 12,
+    snakes: &[
+        link(23, 8),
+        link(49, 30),
+        link(70, 44),
+        link(88, 63),
+        link(101, 77),
+        link(116, 92),
+        link(127, 99),
+        link(139, 112),
+        link(142, 120),
+    ],
+    ladders: &[
+        link(5, 27),
+        link(14, 40),
+        link(33, 58),
+        link(46, 75),
+        link(61, 90),
+        link(79, 104),
+        link(95, 118),
+        link(110, 131),
+    ],
+    safe: &[],
+    specials: &[],
+};
+
+/// Safe tiles and party tiles on a classic sized board.
+pub const PARTY: Preset = Preset {
+    name: "Party",
+    author: "",
+    width: 10,
+    height: 10,
+    snakes: &[link(35, 13), link(67, 47), link(83, 58), link(97, 79)],
+    ladders: &[link(6, 25), link(15, 44), link(40, 61), link(52, 87)],
+    safe: &[10, 30, 50, 70, 90],
+    specials: &[
+        special(19, Special::ExtraRoll),
+        special(28, Special::Teleport),
+        special(45, Special::SkipTurn),
+        special(73, Special::Reverse),
+        special(77, Special::ExtraRoll),
+    ],
+};
+
+/// Every preset, in the order the menu lists them.
+pub const PRESETS: &[Preset] = &[CHUTES_AND_LADDERS, LITTLE_STEPS, GAUNTLET, LONG_HAUL, PARTY];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        analysis,
+        engine::{GameRng, GameState, Player, Rules},
+    };
+    use rand::SeedableRng;
+
+    #[test]
+    fn every_preset_is_a_finishable_board() {
+        for preset in PRESETS {
+            let board = preset.board();
+            assert_eq!((board.width, board.height), (preset.width, preset.height));
+            let odds = analysis::analyze(&board, &Rules::default());
+            assert!(odds.expected_rolls.is_finite(), "{preset}");
+            assert!(odds.unfinished < 0.01, "{preset} rarely finishes");
+            // the analysis starts everyone on the first tile, so must the game
+            let players = vec![Player::new("A"), Player::new("B")];
+            let state = GameState::new(board, players, Rules::default(), GameRng::seed_from_u64(0));
+            for p in &state.players {
+                assert_eq!(state.board.index(p.position), 0, "{preset}");
+            }
+        }
+    }
+}